@@ -0,0 +1,161 @@
+//! Compares `AtomicArc` with `arc_swap::ArcSwap` on `load`, `store`, `swap` and
+//! `compare_exchange`, on a single thread and with several threads sharing the pointer.
+//!
+//! Each operation is first measured on its own. `load_under_store` then loads on all but
+//! one of the threads while the remaining thread keeps storing, which is the read-mostly
+//! workload that both types are built for. The `compare_exchange` loops load while other
+//! threads exchange, like the Treiber stack in the `epoch` bench.
+//!
+//! Run with `cargo bench --bench arc_swap`.
+
+#[cfg(not(feature = "loom"))]
+// the conversions to `Arc` are needed when `feature = "tag"` is enabled
+#[cfg_attr(not(feature = "tag"), allow(clippy::useless_conversion))]
+mod bench {
+    use std::hint::black_box;
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+
+    use arc_swap::ArcSwap;
+    use criterion::{criterion_group, BenchmarkId, Criterion};
+    use nolock::sync::{Atomic, AtomicArc};
+
+    const THREADS: usize = 4;
+    const OPS_PER_THREAD: u64 = 1000;
+
+    /// The operations under test, implemented for both pointer types
+    trait Cell: Sync {
+        fn new(val: u64) -> Self;
+        fn load(&self) -> u64;
+        fn store(&self, val: u64);
+        fn swap(&self, val: u64) -> u64;
+        /// Increments the value with a compare-and-exchange loop
+        fn increment(&self);
+    }
+
+    impl Cell for AtomicArc<u64> {
+        fn new(val: u64) -> Self {
+            AtomicArc::from_arc(Arc::new(val))
+        }
+
+        fn load(&self) -> u64 {
+            *self.load_arc(Ordering::Acquire)
+        }
+
+        fn store(&self, val: u64) {
+            self.store_arc(Arc::new(val), Ordering::Release)
+        }
+
+        fn swap(&self, val: u64) -> u64 {
+            let prev: Arc<u64> = Atomic::swap(self, Arc::new(val), Ordering::AcqRel).into();
+            *prev
+        }
+
+        fn increment(&self) {
+            let mut current = self.load_arc(Ordering::Acquire);
+            loop {
+                let new = Arc::new(*current + 1);
+                match self.compare_exchange_ref(&current, new, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => return,
+                    Err(actual) => current = actual.into(),
+                }
+            }
+        }
+    }
+
+    impl Cell for ArcSwap<u64> {
+        fn new(val: u64) -> Self {
+            ArcSwap::from_pointee(val)
+        }
+
+        fn load(&self) -> u64 {
+            **ArcSwap::load(self)
+        }
+
+        fn store(&self, val: u64) {
+            ArcSwap::store(self, Arc::new(val))
+        }
+
+        fn swap(&self, val: u64) -> u64 {
+            *ArcSwap::swap(self, Arc::new(val))
+        }
+
+        fn increment(&self) {
+            let mut current = self.load_full();
+            loop {
+                let new = Arc::new(*current + 1);
+                let prev = self.compare_and_swap(&current, new);
+                if Arc::ptr_eq(&prev, &current) {
+                    return
+                }
+                current = arc_swap::Guard::into_inner(prev);
+            }
+        }
+    }
+
+    /// Runs `op` `OPS_PER_THREAD` times on each of `threads` threads
+    fn run<C: Cell>(cell: &C, threads: usize, op: impl Fn(&C, u64) + Sync) {
+        if threads == 1 {
+            (0..OPS_PER_THREAD).for_each(|i| op(cell, i));
+            return
+        }
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| (0..OPS_PER_THREAD).for_each(|i| op(cell, i)));
+            }
+        });
+    }
+
+    /// Loads `OPS_PER_THREAD` times on each of `loaders` threads while another thread
+    /// stores as many values
+    fn run_under_store<C: Cell>(cell: &C, loaders: usize) {
+        thread::scope(|s| {
+            s.spawn(|| (0..OPS_PER_THREAD).for_each(|i| cell.store(i)));
+            for _ in 0..loaders {
+                s.spawn(|| (0..OPS_PER_THREAD).for_each(|_| { black_box(cell.load()); }));
+            }
+        });
+    }
+
+    fn bench_cell<C: Cell>(c: &mut Criterion, name: &str) {
+        let mut group = c.benchmark_group(name);
+        for threads in [1, THREADS] {
+            let cell = C::new(0);
+            group.bench_function(BenchmarkId::new("load", threads), |b| {
+                b.iter(|| run(&cell, threads, |cell, _| { black_box(cell.load()); }))
+            });
+            group.bench_function(BenchmarkId::new("store", threads), |b| {
+                b.iter(|| run(&cell, threads, |cell, i| cell.store(i)))
+            });
+            group.bench_function(BenchmarkId::new("swap", threads), |b| {
+                b.iter(|| run(&cell, threads, |cell, i| { black_box(cell.swap(i)); }))
+            });
+            group.bench_function(BenchmarkId::new("compare_exchange", threads), |b| {
+                b.iter(|| run(&cell, threads, |cell, _| cell.increment()))
+            });
+        }
+        let cell = C::new(0);
+        group.bench_function(BenchmarkId::new("load_under_store", THREADS), |b| {
+            b.iter(|| run_under_store(&cell, THREADS - 1))
+        });
+        group.finish();
+    }
+
+    fn atomic_arc(c: &mut Criterion) {
+        bench_cell::<AtomicArc<u64>>(c, "AtomicArc");
+    }
+
+    fn arc_swap(c: &mut Criterion) {
+        bench_cell::<ArcSwap<u64>>(c, "ArcSwap");
+    }
+
+    criterion_group!(benches, atomic_arc, arc_swap);
+}
+
+#[cfg(not(feature = "loom"))]
+criterion::criterion_main!(bench::benches);
+
+// the pointer types are built on `loom::sync::Arc` with `feature = "loom"`
+#[cfg(feature = "loom")]
+fn main() {}
@@ -0,0 +1,124 @@
+//! Compares reference counting (`AtomicArc`) with epoch-based reclamation
+//! (`EpochArc`) on a Treiber stack, and `EpochArc::load` with
+//! `EpochArc::load_snapshot` under contention.
+//!
+//! Run with `cargo bench --features crossbeam-epoch --bench epoch`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use nolock::sync::{Atomic, AtomicArc, EpochArc};
+
+const THREADS: usize = 4;
+const OPS_PER_THREAD: u64 = 1000;
+
+struct Node {
+    value: u64,
+    next: Option<Arc<Node>>,
+}
+
+/// A Treiber stack over an atomic `Arc` pointer, whose bottom node is a sentinel
+struct Stack<A> {
+    head: A,
+}
+
+impl<A> Stack<A>
+where
+    A: Atomic,
+    A::Elem: Into<Arc<Node>>,
+    Arc<Node>: Into<A::Elem>,
+{
+    fn push(&self, value: u64) {
+        loop {
+            let head: Arc<Node> = self.head.load(Ordering::Acquire).into();
+            let node = Arc::new(Node { value, next: Some(head.clone()) });
+            if self.head.compare_exchange(head, node, Ordering::Release, Ordering::Relaxed).is_ok() {
+                return
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<u64> {
+        loop {
+            let head: Arc<Node> = self.head.load(Ordering::Acquire).into();
+            let next = head.next.clone()?;
+            if self.head.compare_exchange(head.clone(), next, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                return Some(head.value)
+            }
+        }
+    }
+}
+
+fn sentinel() -> Arc<Node> {
+    Arc::new(Node { value: 0, next: None })
+}
+
+fn push_and_pop<A>(stack: &Stack<A>)
+where
+    A: Atomic + Sync,
+    A::Elem: Into<Arc<Node>>,
+    Arc<Node>: Into<A::Elem>,
+{
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for i in 0..OPS_PER_THREAD {
+                    stack.push(i);
+                    stack.pop();
+                }
+            });
+        }
+    });
+}
+
+fn treiber_stack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("treiber_stack");
+
+    let stack = Stack { head: AtomicArc::from_arc(sentinel()) };
+    group.bench_function("AtomicArc", |b| b.iter(|| push_and_pop(&stack)));
+
+    let stack = Stack { head: EpochArc::new(sentinel()) };
+    group.bench_function("EpochArc", |b| b.iter(|| push_and_pop(&stack)));
+
+    group.finish();
+}
+
+/// Runs `read` on every reader thread while one thread keeps storing new values
+fn read_while_storing(cell: &EpochArc<u64>, read: impl Fn(&EpochArc<u64>) -> u64 + Sync) {
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            let mut i = 0;
+            while !done.load(Ordering::Relaxed) {
+                cell.store(i, Ordering::Release);
+                i += 1;
+            }
+        });
+        let readers: Vec<_> = (0..THREADS)
+            .map(|_| s.spawn(|| (0..OPS_PER_THREAD).map(|_| read(cell)).sum::<u64>()))
+            .collect();
+        for reader in readers {
+            std::hint::black_box(reader.join().unwrap());
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+fn contended_load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contended_load");
+    let cell = EpochArc::new(0);
+
+    group.bench_function("load", |b| {
+        b.iter(|| read_while_storing(&cell, |cell| *cell.load(Ordering::Acquire)))
+    });
+    group.bench_function("load_snapshot", |b| {
+        b.iter(|| read_while_storing(&cell, |cell| *cell.load_snapshot(Ordering::Acquire)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, treiber_stack, contended_load);
+criterion_main!(benches);
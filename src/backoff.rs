@@ -0,0 +1,87 @@
+//! Exponential backoff for compare-and-swap retry loops.
+
+use core::cell::Cell;
+
+use crate::primitives::{spin_loop, yield_now};
+
+/// The step after which `snooze` yields instead of spinning
+const SPIN_LIMIT: u32 = 6;
+
+/// Exponential backoff for retry loops, modeled on `crossbeam_utils::Backoff`
+///
+/// Each call to [`snooze`](Backoff::snooze) spins for twice as long as the previous
+/// one. Once the spin limit is reached, it yields the thread to the scheduler instead,
+/// or keeps spinning without `feature = "std"`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use nolock::backoff::Backoff;
+///
+/// let counter = AtomicUsize::new(0);
+/// let backoff = Backoff::new();
+/// let mut current = counter.load(Ordering::Relaxed);
+/// while let Err(actual) = counter.compare_exchange_weak(current, current + 1, Ordering::AcqRel, Ordering::Relaxed) {
+///     current = actual;
+///     backoff.snooze();
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Backoff {
+    step: Cell<u32>,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self {
+            step: Cell::new(0),
+        }
+    }
+
+    /// Resets the backoff to its initial state
+    pub fn reset(&self) {
+        self.step.set(0);
+    }
+
+    /// Backs off in a retry loop
+    ///
+    /// Spins for an exponentially growing number of iterations, and yields the thread
+    /// once the spin limit is reached.
+    pub fn snooze(&self) {
+        let step = self.step.get();
+        if step <= SPIN_LIMIT {
+            for _ in 0..1 << step {
+                spin_loop();
+            }
+            self.step.set(step + 1);
+        } else {
+            yield_now();
+        }
+    }
+
+    /// Returns `true` once `snooze` yields the thread instead of spinning
+    pub fn is_yielding(&self) -> bool {
+        self.step.get() > SPIN_LIMIT
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yields_after_spin_limit() {
+        let backoff = Backoff::new();
+        for _ in 0..=SPIN_LIMIT {
+            assert!(!backoff.is_yielding());
+            backoff.snooze();
+        }
+        assert!(backoff.is_yielding());
+        backoff.snooze();
+        assert!(backoff.is_yielding());
+
+        backoff.reset();
+        assert!(!backoff.is_yielding());
+    }
+}
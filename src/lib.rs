@@ -1,3 +1,54 @@
+//! Atomic reference-counted pointers that can carry a tag in the unused low bits.
+//!
+//! The pointer types live in [`sync`], and the most used ones are re-exported at the
+//! crate root. [`stack`] and `set` hold the lock-free collections built on them, with
+//! `feature = "collections"`. `set` hashes its values and also needs `feature = "std"`.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::sync::atomic::Ordering;
+//! use nolock::{Atomic, AtomicArc};
+//! # #[cfg(feature = "tag")]
+//! use nolock::TaggedArc;
+//! # #[cfg(feature = "collections")]
+//! use nolock::stack::Stack;
+//!
+//! let cell = AtomicArc::new(1);
+//! cell.store(Arc::new(2), Ordering::Release);
+//!
+//! # #[cfg(feature = "tag")]
+//! # {
+//! let tagged = TaggedArc::from_arc_with_tag(Arc::new(3), 0b1);
+//! assert_eq!((*tagged, tagged.tag()), (3, 0b1));
+//! # }
+//!
+//! # #[cfg(feature = "collections")]
+//! # {
+//! let stack = Stack::new();
+//! stack.push(4);
+//! assert_eq!(stack.pop(), Some(4));
+//! # }
+//! ```
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+extern crate alloc;
+
+mod primitives;
+
+pub mod backoff;
+
+pub mod raw;
+
 pub mod sync;
+pub use sync::{Atomic, AtomicArc};
+#[cfg(feature = "tag")]
+pub use sync::TaggedArc;
+
+#[cfg(feature = "collections")]
+pub mod stack;
 
-mod stack;
+#[cfg(all(feature = "collections", feature = "std"))]
+pub mod set;
@@ -0,0 +1,64 @@
+//! The atomic and reference counting primitives used by the crate.
+//!
+//! With `feature = "loom"` enabled, these are replaced by their `loom` counterparts so
+//! that the atomic operations can be model checked.
+//!
+//! With `feature = "portable-atomic"` enabled, `AtomicUsize` and `AtomicPtr` are provided
+//! by the `portable-atomic` crate instead, and so is `Arc` on targets without a native
+//! compare-and-swap, where `alloc::sync` does not exist. Such a target is checked with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg portable_atomic_unsafe_assume_single_core" cargo +nightly build \
+//!     -Zbuild-std=core,alloc --target thumbv6m-none-eabi --no-default-features --features portable-atomic
+//! ```
+
+#[cfg(all(not(feature = "loom"), any(not(feature = "portable-atomic"), target_has_atomic = "ptr")))]
+pub(crate) use alloc::sync::{Arc, Weak};
+// `alloc::sync` is not available on targets without a native compare-and-swap
+#[cfg(all(not(feature = "loom"), feature = "portable-atomic", not(target_has_atomic = "ptr")))]
+pub(crate) use portable_atomic_util::{Arc, Weak};
+#[cfg(all(not(feature = "loom"), not(feature = "portable-atomic")))]
+pub(crate) use core::sync::atomic::{AtomicPtr, AtomicUsize};
+// for targets without a native compare-and-swap
+#[cfg(all(not(feature = "loom"), feature = "portable-atomic"))]
+pub(crate) use portable_atomic::{AtomicPtr, AtomicUsize};
+
+#[cfg(feature = "loom")]
+pub(crate) use loom::sync::Arc;
+#[cfg(feature = "loom")]
+pub(crate) use loom::sync::atomic::{AtomicPtr, AtomicUsize};
+
+#[cfg(all(feature = "collections", feature = "loom"))]
+pub(crate) use loom::cell::UnsafeCell;
+
+#[cfg(not(feature = "loom"))]
+pub(crate) use core::hint::spin_loop;
+#[cfg(feature = "loom")]
+pub(crate) use loom::hint::spin_loop;
+
+#[cfg(all(not(feature = "loom"), feature = "std"))]
+pub(crate) use std::thread::yield_now;
+#[cfg(feature = "loom")]
+pub(crate) use loom::thread::yield_now;
+
+/// Without `std` there is no scheduler to yield to, so this only spins
+#[cfg(all(not(feature = "loom"), not(feature = "std")))]
+pub(crate) fn yield_now() {
+    spin_loop();
+}
+
+/// A `core::cell::UnsafeCell` with the closure based API of `loom::cell::UnsafeCell`
+#[cfg(all(feature = "collections", not(feature = "loom")))]
+#[derive(Debug)]
+pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(all(feature = "collections", not(feature = "loom")))]
+impl<T> UnsafeCell<T> {
+    pub(crate) fn new(data: T) -> Self {
+        Self(core::cell::UnsafeCell::new(data))
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
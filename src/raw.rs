@@ -0,0 +1,99 @@
+//! Tagging functions for pointer addresses.
+//!
+//! These operate on plain `usize` addresses, not on owned pointers, so they never
+//! touch a reference count. They are the building blocks of the tagged pointers in
+//! [`sync`](crate::sync), for users who build their own atomic structures on `usize`.
+//!
+//! The tag of a pointer to `T` resides on the low bits that are always zero because of
+//! the alignment of `T`.
+//!
+//! # Examples
+//!
+//! ```
+//! use nolock::raw;
+//!
+//! let value = 5u64;
+//! let addr = &value as *const u64 as usize;
+//!
+//! let tagged = raw::compose_tag::<u64>(addr, 0b101);
+//! assert_eq!(raw::decompose_tag::<u64>(tagged), (addr, 0b101));
+//! ```
+
+use core::mem;
+
+/// Returns a bitmask containing the unused least significant bits of an aligned pointer to `T`.
+#[inline]
+pub const fn low_bits<T>() -> usize {
+    (1 << mem::align_of::<T>().trailing_zeros()) - 1
+}
+
+/// Returns the number of unused low bits of an aligned pointer to `T`
+#[inline]
+pub const fn tag_bits<T>() -> u32 {
+    mem::align_of::<T>().trailing_zeros()
+}
+
+/// Returns the largest tag that fits into the unused low bits of an aligned pointer to `T`
+#[inline]
+pub const fn max_tag<T>() -> usize {
+    low_bits::<T>()
+}
+
+/// Given a tagged pointer `data`, returns the same pointer, but tagged with `tag`.
+///
+/// `tag` is truncated to fit into the unused bits of the pointer to `T`.
+#[inline]
+pub fn compose_tag<T>(data: usize, tag: usize) -> usize {
+    let mask = low_bits::<T>();
+    (data & !mask) | (tag & mask)
+}
+
+/// Decomposes a tagged pointer `data` into the pointer and the tag.
+#[inline]
+pub fn decompose_tag<T>(data: usize) -> (usize, usize) {
+    let mask = low_bits::<T>();
+    (data & !mask, data & mask)
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+
+    #[repr(align(16))]
+    struct Align16;
+
+    #[test]
+    fn bits_per_alignment() {
+        assert_eq!((low_bits::<u8>(), tag_bits::<u8>(), max_tag::<u8>()), (0, 0, 0));
+        assert_eq!((low_bits::<u16>(), tag_bits::<u16>(), max_tag::<u16>()), (0b1, 1, 0b1));
+        assert_eq!((low_bits::<u32>(), tag_bits::<u32>(), max_tag::<u32>()), (0b11, 2, 0b11));
+        assert_eq!((low_bits::<Align16>(), tag_bits::<Align16>(), max_tag::<Align16>()), (0b1111, 4, 0b1111));
+    }
+
+    #[test]
+    fn compose_and_decompose_per_alignment() {
+        let addr = 0x1000;
+
+        assert_eq!(compose_tag::<u8>(addr, 0b1), addr);
+        assert_eq!(decompose_tag::<u8>(addr), (addr, 0));
+
+        let tagged = compose_tag::<u16>(addr, 0b1);
+        assert_eq!(tagged, addr | 0b1);
+        assert_eq!(decompose_tag::<u16>(tagged), (addr, 0b1));
+
+        let tagged = compose_tag::<u32>(addr, 0b11);
+        assert_eq!(decompose_tag::<u32>(tagged), (addr, 0b11));
+
+        let tagged = compose_tag::<Align16>(addr, 0b1010);
+        assert_eq!(decompose_tag::<Align16>(tagged), (addr, 0b1010));
+    }
+
+    #[test]
+    fn compose_replaces_and_truncates_tag() {
+        let addr = 0x1000;
+        let tagged = compose_tag::<u32>(addr, 0b01);
+        assert_eq!(compose_tag::<u32>(tagged, 0b10), addr | 0b10);
+        // the bits that do not fit are discarded
+        assert_eq!(compose_tag::<u32>(addr, 0b111), addr | 0b11);
+    }
+}
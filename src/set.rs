@@ -0,0 +1,261 @@
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    sync::atomic::Ordering,
+};
+use std::collections::hash_map::RandomState;
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::backoff::Backoff;
+use crate::primitives::Arc;
+
+use crate::sync::{Atomic, AtomicArc};
+
+/// The number of buckets of a set created with [`Set::new`]
+const DEFAULT_BUCKETS: usize = 64;
+
+struct Node<T> {
+    // `None` for the sentinel at the end of every bucket
+    value: Option<Arc<T>>,
+    next: Option<Arc<Node<T>>>,
+}
+
+impl<T> Node<T> {
+    fn sentinel() -> Self {
+        Self {
+            value: None,
+            next: None,
+        }
+    }
+}
+
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        // unlink the chain iteratively to avoid a deep recursion
+        let mut next = self.next.take();
+        while let Some(node) = next {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// A lock-free, insert-only hash set of shared values
+///
+/// Each bucket is an `AtomicArc` pointing to a chain of nodes that ends in a
+/// sentinel, and a value is inserted by exchanging the head of its bucket for a
+/// new node. Nodes are never removed, so a loaded chain can be walked without
+/// further synchronization. The number of buckets is fixed when the set is
+/// created.
+///
+/// The main use is [`intern`](Set::intern), which returns the same `Arc` for
+/// equal values, so that they share one allocation.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use nolock::set::Set;
+///
+/// let set = Set::new();
+/// let first = set.intern(String::from("hello"));
+/// let second = set.intern(String::from("hello"));
+/// assert!(Arc::ptr_eq(&first, &second));
+/// assert!(set.contains("hello"));
+/// ```
+pub struct Set<T, S = RandomState> {
+    buckets: Box<[AtomicArc<Node<T>>]>,
+    hasher: S,
+}
+
+impl<T> Set<T> {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::with_buckets(DEFAULT_BUCKETS)
+    }
+
+    /// Creates an empty set with `buckets` buckets
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn with_buckets(buckets: usize) -> Self {
+        Self::with_buckets_and_hasher(buckets, RandomState::new())
+    }
+}
+
+impl<T> Default for Set<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, S> Set<T, S> {
+    /// Creates an empty set with `buckets` buckets that hashes the values with `hasher`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn with_buckets_and_hasher(buckets: usize, hasher: S) -> Self {
+        assert!(buckets > 0, "a set needs at least one bucket");
+        Self {
+            buckets: (0..buckets).map(|_| AtomicArc::new(Node::sentinel())).collect::<Vec<_>>().into(),
+            hasher,
+        }
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> Set<T, S> {
+    /// Returns the value that was interned for `value`, or interns `value` and
+    /// returns it
+    ///
+    /// Every call with an equal value returns a clone of the same `Arc`, also when
+    /// several threads intern equal values at the same time.
+    #[allow(clippy::useless_conversion)]
+    pub fn intern(&self, value: T) -> Arc<T> {
+        let bucket = self.bucket(&value);
+
+        let backoff = Backoff::new();
+        // the chain is read through the head, so the loads acquire the inserts
+        let mut head = bucket.load(Ordering::Acquire);
+        if let Some(found) = find(&head.clone().into(), &value) {
+            return found
+        }
+        let mut node = Arc::new(Node {
+            value: Some(Arc::new(value)),
+            next: None,
+        });
+        loop {
+            // the node is not shared until the exchange succeeds
+            Arc::get_mut(&mut node)
+                .expect("unpublished node must be unique")
+                .next = Some(head.clone().into());
+
+            match bucket.compare_exchange_weak(head, node.clone(), Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return node.value.clone().expect("inserted node holds a value"),
+                Err(actual) => head = actual,
+            }
+            // an equal value may have been inserted in the meantime
+            let value = node.value.as_deref().expect("inserted node holds a value");
+            if let Some(found) = find(&head.clone().into(), value) {
+                return found
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Returns the value that was interned for `value`, if any
+    #[allow(clippy::useless_conversion)]
+    pub fn get<Q>(&self, value: &Q) -> Option<Arc<T>>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        find(&self.bucket(value).load(Ordering::Acquire).into(), value)
+    }
+
+    /// Returns `true` if a value equal to `value` is in the set
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    /// Returns the bucket that `value` belongs to
+    fn bucket<Q: Hash + ?Sized>(&self, value: &Q) -> &AtomicArc<Node<T>> {
+        let hash = self.hasher.hash_one(value);
+        &self.buckets[(hash % self.buckets.len() as u64) as usize]
+    }
+}
+
+/// Returns the value of the chain starting at `head` that equals `value`
+fn find<T, Q>(head: &Arc<Node<T>>, value: &Q) -> Option<Arc<T>>
+where
+    T: Borrow<Q>,
+    Q: Eq + ?Sized,
+{
+    let mut node: &Node<T> = head;
+    loop {
+        if let Some(found) = &node.value {
+            if (**found).borrow() == value {
+                return Some(found.clone())
+            }
+        }
+        // the chain below `head` is kept alive by `head` and never changes
+        node = node.next.as_deref()?;
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn intern_shares_equal_values() {
+        let set = Set::with_buckets(4);
+        let a = set.intern(String::from("a"));
+        let b = set.intern(String::from("b"));
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert!(Arc::ptr_eq(&a, &set.intern(String::from("a"))));
+
+        assert!(Arc::ptr_eq(&set.get("b").unwrap(), &b));
+        assert!(set.get("c").is_none());
+        assert!(!set.contains("c"));
+    }
+
+    #[test]
+    fn single_bucket_chains_values() {
+        let set = Set::with_buckets(1);
+        let values: Vec<_> = (0..100).map(|i| set.intern(i)).collect();
+        for (i, value) in values.iter().enumerate() {
+            assert!(Arc::ptr_eq(&set.get(&(i as i32)).unwrap(), value));
+        }
+    }
+
+    #[test]
+    fn drop_releases_values() {
+        let set = Set::new();
+        let value = set.intern(13);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(set);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn concurrent_intern_returns_one_arc() {
+        const THREADS: usize = 8;
+        const VALUES: u32 = 50;
+
+        // few buckets, so that the threads race on the same chains
+        let set = Set::with_buckets(4);
+        let interned: Vec<Vec<Arc<u32>>> = thread::scope(|s| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let set = &set;
+                    s.spawn(move || {
+                        // each thread interns the values in a different order
+                        (0..VALUES)
+                            .map(|i| (i + t as u32 * 7) % VALUES)
+                            .map(|i| set.intern(i))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        for values in &interned {
+            for value in values {
+                let expected = set.get(value).unwrap();
+                assert!(Arc::ptr_eq(value, &expected));
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use core::{iter::FromIterator, sync::atomic::Ordering};
+
+use crate::backoff::Backoff;
+use crate::primitives::{Arc, UnsafeCell};
+
+use crate::sync::{Atomic, AtomicArc};
+
+struct Node<T> {
+    // `None` for the sentinel at the bottom of the stack and for popped nodes
+    data: UnsafeCell<Option<T>>,
+    next: Option<Arc<Node<T>>>,
+}
+
+// SAFETY: `data` is only accessed by the thread that pushes the node and by
+// the thread that pops it, which are synchronized by the exchange on `head`
+unsafe impl<T: Send> Send for Node<T> {}
+unsafe impl<T: Send> Sync for Node<T> {}
+
+impl<T> Node<T> {
+    fn sentinel() -> Self {
+        Self {
+            data: UnsafeCell::new(None),
+            next: None,
+        }
+    }
+}
+
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        // unlink the chain iteratively to avoid a deep recursion
+        let mut next = self.next.take();
+        while let Some(node) = next {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// A lock-free Treiber stack
+///
+/// The head of the stack is an `AtomicArc` that always points to a node. The
+/// bottom of the stack is a sentinel node without data, so an empty stack is
+/// one whose head is the sentinel.
+///
+/// # Examples
+///
+/// ```
+/// use nolock::stack::Stack;
+///
+/// let stack = Stack::new();
+/// stack.push(1);
+/// stack.push(2);
+/// assert_eq!(stack.pop(), Some(2));
+/// assert_eq!(stack.pop(), Some(1));
+/// assert_eq!(stack.pop(), None);
+/// ```
+///
+/// Another thread may be loading the head while a pop unlinks it. `AtomicArc`
+/// releases the popped node only once no load of the head is in progress, so that
+/// thread still takes its reference to a live node.
+pub struct Stack<T> {
+    head: AtomicArc<Node<T>>,
+}
+
+impl<T> Stack<T> {
+    /// Creates an empty stack
+    pub fn new() -> Self {
+        Self {
+            head: AtomicArc::new(Node::sentinel()),
+        }
+    }
+
+    /// Pushes a value on top of the stack
+    #[allow(clippy::useless_conversion)]
+    pub fn push(&self, val: T) {
+        let mut node = Arc::new(Node {
+            data: UnsafeCell::new(Some(val)),
+            next: None,
+        });
+
+        let backoff = Backoff::new();
+        // the head is cloned into the new node, which reads the node that it points to,
+        // so the loads acquire the push that published it
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            // the node is not shared until the exchange succeeds
+            Arc::get_mut(&mut node)
+                .expect("unpublished node must be unique")
+                .next = Some(head.clone().into());
+
+            match self.head.compare_exchange_weak(head, node.clone(), Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return,
+                Err(actual) => head = actual,
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Removes the value on top of the stack and returns it, or `None` if the
+    /// stack is empty
+    #[allow(clippy::useless_conversion)]
+    pub fn pop(&self) -> Option<T> {
+        let backoff = Backoff::new();
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            let node: Arc<Node<T>> = head.clone().into();
+            let next = node.next.clone()?;
+
+            match self.head.compare_exchange_weak(head, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => {
+                    // SAFETY: only the thread that unlinked the node can reach its data
+                    return node.data.with_mut(|data| unsafe { (*data).take() });
+                }
+                Err(actual) => head = actual,
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Takes every value off the stack at once and returns them in LIFO order
+    ///
+    /// The whole chain is unlinked with a single swap of the head, so the returned
+    /// iterator does not touch the stack any more. Values pushed concurrently are
+    /// either taken by this call or left on the stack.
+    #[allow(clippy::useless_conversion)]
+    pub fn drain(&self) -> Drain<T> {
+        let head: Arc<Node<T>> = self.head
+            .swap(Arc::new(Node::sentinel()), Ordering::AcqRel)
+            .into();
+        Drain {
+            next: Some(head),
+        }
+    }
+
+    /// Returns `true` if the stack is empty at the time of the call
+    #[allow(clippy::useless_conversion)]
+    pub fn is_empty(&self) -> bool {
+        let head: Arc<Node<T>> = self.head.load(Ordering::Acquire).into();
+        head.next.is_none()
+    }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pushes the values in iteration order, so the last value is on top and is
+/// popped first.
+///
+/// ```
+/// use nolock::stack::Stack;
+///
+/// let stack: Stack<_> = (0..3).collect();
+/// assert_eq!(stack.pop(), Some(2));
+/// ```
+impl<T> FromIterator<T> for Stack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let stack = Self::new();
+        for val in iter {
+            stack.push(val);
+        }
+        stack
+    }
+}
+
+/// An iterator over the values taken off a stack by [`Stack::drain`]
+///
+/// Values that are not yielded are dropped with the iterator.
+pub struct Drain<T> {
+    next: Option<Arc<Node<T>>>,
+}
+
+impl<T> Iterator for Drain<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.next.take()?;
+        self.next = node.next.clone();
+        // SAFETY: the chain was unlinked by `drain`, so no other thread can reach its data.
+        // The data of the sentinel at the bottom is `None`, which ends the iteration.
+        node.data.with_mut(|data| unsafe { (*data).take() })
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn push_and_pop() {
+        let stack = Stack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        assert!(!stack.is_empty());
+
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn drain_in_lifo_order() {
+        const N: usize = 100;
+
+        let stack = Stack::new();
+        for i in 0..N {
+            stack.push(i);
+        }
+
+        let drained: Vec<_> = stack.drain().collect();
+        assert_eq!(drained.len(), N);
+        assert!(drained.iter().rev().copied().eq(0..N));
+        assert!(stack.is_empty());
+
+        stack.push(N);
+        assert_eq!(stack.drain().next(), Some(N));
+        assert_eq!(stack.drain().next(), None);
+    }
+
+    #[test]
+    fn collect_pops_in_reverse() {
+        let stack: Stack<u32> = (0..10).collect();
+        let popped: Vec<_> = core::iter::from_fn(|| stack.pop()).collect();
+        assert!(popped.into_iter().eq((0..10).rev()));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "too slow under Miri")]
+    fn drop_long_stack() {
+        let stack = Stack::new();
+        for i in 0..100_000 {
+            stack.push(i);
+        }
+        drop(stack);
+    }
+
+    #[test]
+    fn concurrent_push_and_pop() {
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 1000;
+
+        let stack = Arc::new(Stack::new());
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let stack = stack.clone();
+                thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for i in 0..PER_THREAD {
+                        stack.push(t * PER_THREAD + i);
+                        if i % 2 == 0 {
+                            popped.extend(stack.pop());
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut values: Vec<usize> = handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        while let Some(val) = stack.pop() {
+            values.push(val);
+        }
+
+        let unique: HashSet<_> = values.iter().copied().collect();
+        assert_eq!(values.len(), THREADS * PER_THREAD);
+        assert_eq!(unique.len(), THREADS * PER_THREAD);
+    }
+}
@@ -1,7 +1,68 @@
-use std::sync::atomic::Ordering;
+use core::sync::atomic::Ordering;
 
+use crate::backoff::Backoff;
+
+/// Returns the strongest ordering that is allowed as the failure ordering
+/// of a compare-and-exchange whose success ordering is `order`.
+#[inline]
+pub(crate) fn failure_ordering(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Release | Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::Acquire | Ordering::AcqRel => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    }
+}
+
+/// Panics with a descriptive message if `order` is not a valid ordering for a load.
+#[inline]
+pub(crate) fn assert_load_ordering(order: Ordering) {
+    match order {
+        Ordering::Release => panic!("there is no such thing as a release load"),
+        Ordering::AcqRel => panic!("there is no such thing as an acquire-release load"),
+        _ => {}
+    }
+}
+
+/// Panics with a descriptive message if `order` is not a valid ordering for a store.
+#[inline]
+pub(crate) fn assert_store_ordering(order: Ordering) {
+    match order {
+        Ordering::Acquire => panic!("there is no such thing as an acquire store"),
+        Ordering::AcqRel => panic!("there is no such thing as an acquire-release store"),
+        _ => {}
+    }
+}
+
+/// Panics with a descriptive message if `order` is not a valid failure ordering
+/// for a compare-and-exchange.
+#[inline]
+pub(crate) fn assert_failure_ordering(order: Ordering) {
+    match order {
+        Ordering::Release => panic!("there is no such thing as a release failure ordering"),
+        Ordering::AcqRel => panic!("there is no such thing as an acquire-release failure ordering"),
+        _ => {}
+    }
+}
+
+/// Atomic operations on a shared pointer
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::Ordering;
+/// use nolock::sync::{Atomic, AtomicArc};
+///
+/// let cell = AtomicArc::new(1);
+/// let current = cell.load(Ordering::Acquire);
+/// assert!(cell.compare_exchange(current, Arc::new(2), Ordering::AcqRel, Ordering::Acquire).is_ok());
+///
+/// let current: Arc<i32> = cell.load(Ordering::Acquire).into();
+/// assert_eq!(*current, 2);
+/// ```
 pub trait Atomic {
-    type Target;
+    /// The pointer type that is loaded from and stored into the atomic
+    type Elem;
 
     /// Loads a value from the atomic pointer.
     ///
@@ -12,7 +73,8 @@ pub trait Atomic {
     /// # Panics
     /// 
     /// Panics if `order` is `Release` or `AcqRel`.
-    fn load(&self, order: Ordering) -> Self::Target;
+    #[must_use = "loading a value has no effect other than returning a new reference"]
+    fn load(&self, order: Ordering) -> Self::Elem;
     
     /// Stores a value into the pointer
     ///
@@ -23,20 +85,22 @@ pub trait Atomic {
     /// # Panics
     /// 
     /// Panics if `order` is `Acquire` or `AcqRel`.
-    fn store(&self, new: impl Into<Self::Target>, order: Ordering);
+    fn store(&self, new: impl Into<Self::Elem>, order: Ordering);
     
     /// Stores a `TaggedArc` pointer into the atomic pointer, returning the previously stored pointer
     ///
     /// swap takes an `Ordering` argument which describes the memory ordering of this operation. 
     /// All ordering modes are possible. Note that using `Acquire` makes the store part of this 
     /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
-    fn swap(&self, new: impl Into<Self::Target>, order: Ordering) -> Self::Target;
+    #[must_use = "use `store` if the previous value is not needed"]
+    fn swap(&self, new: impl Into<Self::Elem>, order: Ordering) -> Self::Elem;
 
     /// Stores a `TaggedArc` pointer into the if the current value is the same as the `current` value.
     /// The tag will also be compared.
     ///
     /// The return value is a result indicating whether the new value was written and containing
     /// the previous value. On success this value is guaranteed to be equal to `current`.
+    /// `current` is only used for the comparison, and `new` is dropped if it is not written.
     ///
     /// `compare_exchange` takes two [`Ordering`] arguments to describe the memory
     /// ordering of this operation. `success` describes the required ordering for the
@@ -46,20 +110,26 @@ pub trait Atomic {
     /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
     /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
     /// and must be equivalent to or weaker than the success ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    #[must_use = "the result tells whether the value was written"]
     fn compare_exchange(
         &self,
-        current: impl Into<Self::Target>,
-        new: impl Into<Self::Target>,
+        current: impl Into<Self::Elem>,
+        new: impl Into<Self::Elem>,
         success: Ordering,
         failure: Ordering,
-    ) -> Result<Self::Target, Self::Target>;
+    ) -> Result<Self::Elem, Self::Elem>;
 
     /// Stores an `Arc` pointer into the atomic pointer if the current value is the same as the `current` value.
     ///
     /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
     /// comparison succeeds, which can result in more efficient code on some platforms. The
     /// return value is a result indicating whether the new value was written and containing the
-    /// previous value.
+    /// previous value. `current` is only used for the comparison, and `new` is dropped if it is
+    /// not written.
     ///
     /// `compare_exchange_weak` takes two [`Ordering`] arguments to describe the memory
     /// ordering of this operation. `success` describes the required ordering for the
@@ -69,13 +139,40 @@ pub trait Atomic {
     /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
     /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
     /// and must be equivalent to or weaker than the success ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    #[must_use = "the result tells whether the value was written, which may fail spuriously"]
     fn compare_exchange_weak(
         &self,
-        current: impl Into<Self::Target>,
-        new: impl Into<Self::Target>,
+        current: impl Into<Self::Elem>,
+        new: impl Into<Self::Elem>,
+        success: Ordering,
+        failure: Ordering
+    ) -> Result<Self::Elem, Self::Elem>;
+
+    /// Stores `new` into the atomic pointer if the current value is the same as the `current`
+    /// value, like [`compare_exchange_weak`], but hands `new` back instead of dropping it.
+    ///
+    /// On success, returns the previous value together with a new reference to `new`, which
+    /// is taken before another thread can replace it. On failure, which may be spurious,
+    /// returns the current value together with `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange_weak`]: Atomic::compare_exchange_weak
+    #[must_use = "the result tells whether the value was written, which may fail spuriously"]
+    #[allow(clippy::type_complexity)]
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Self::Elem>,
+        new: Self::Elem,
         success: Ordering,
         failure: Ordering
-    ) -> Result<Self::Target, Self::Target>;
+    ) -> Result<(Self::Elem, Self::Elem), (Self::Elem, Self::Elem)>;
 
     /// Fetches the value, and applies a function to it that returns an optional
     /// new value. Returns a `Result` of `Ok(previous_value)` if the function
@@ -99,22 +196,84 @@ pub trait Atomic {
     /// load [`Relaxed`]. The (failed) load ordering can only be [`SeqCst`],
     /// [`Acquire`] or [`Relaxed`] and must be equivalent to or weaker than the
     /// success ordering.
+    #[must_use = "the result tells whether the value was updated"]
     fn fetch_update<F>(
         &self,
         set_order: Ordering,
         fetch_order: Ordering,
         mut f: F 
-    ) -> Result<Self::Target, Self::Target>
+    ) -> Result<Self::Elem, Self::Elem>
     where 
-        F: FnMut(&Self::Target) -> Option<Self::Target>
+        F: FnMut(&Self::Elem) -> Option<Self::Elem>
     {
+        let backoff = Backoff::new();
         let mut prev = self.load(fetch_order);
         while let Some(next) = f(&prev) {
             match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
                 x @ Ok(_) => return x,
                 Err(next_prev) => prev = next_prev
             }
+            backoff.snooze();
+        }
+        Err(prev)
+    }
+
+    /// Fetches the value, and applies a function to it that returns an optional
+    /// new value. Returns a `Result` of `Ok(new_value)` if the function returned
+    /// `Some(_)`, else `Err(previous_value)`.
+    ///
+    /// This is [`fetch_update`](Atomic::fetch_update), except that `Ok` holds the value
+    /// that was installed rather than the one it replaced.
+    #[must_use = "the result tells whether the value was updated"]
+    fn fetch_update_new<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F
+    ) -> Result<Self::Elem, Self::Elem>
+    where
+        F: FnMut(&Self::Elem) -> Option<Self::Elem>
+    {
+        let backoff = Backoff::new();
+        let mut prev = self.load(fetch_order);
+        while let Some(next) = f(&prev) {
+            // `next` is only referenced again once it is installed
+            match self.compare_exchange_weak_keep(prev, next, set_order, fetch_order) {
+                Ok((_, installed)) => return Ok(installed),
+                Err((next_prev, _)) => prev = next_prev
+            }
+            backoff.snooze();
         }
         Err(prev)
     }
+
+    /// Fetches the value and replaces it with the value returned by `f`, retrying until
+    /// the replacement succeeds. Returns the previous value.
+    ///
+    /// Unlike [`fetch_update`](Atomic::fetch_update), `f` always produces a new value and
+    /// the update is never given up. `f` may be called multiple times if the value has
+    /// been changed from other threads in the meantime.
+    ///
+    /// Note: This does not protect the program from the ABA problem.
+    ///
+    /// `update` takes an `Ordering` argument which describes the memory ordering of the
+    /// successful compare-and-exchange. The loads use the strongest failure ordering
+    /// allowed for `order`.
+    fn update<F>(&self, order: Ordering, mut f: F) -> Self::Elem
+    where
+        F: FnMut(&Self::Elem) -> Self::Elem
+    {
+        let fetch_order = failure_ordering(order);
+        let backoff = Backoff::new();
+        let mut prev = self.load(fetch_order);
+        loop {
+            let next = f(&prev);
+            match self.compare_exchange_weak(prev, next, order, fetch_order) {
+                Ok(prev) => return prev,
+                Err(next_prev) => prev = next_prev
+            }
+            backoff.snooze();
+        }
+    }
 }
+
@@ -0,0 +1,380 @@
+use core::{marker::PhantomData, ops::Deref, sync::atomic::Ordering};
+use alloc::sync::Arc;
+
+use crossbeam_epoch::{self as epoch, Atomic as EpochAtomic, Guard, Owned};
+
+use crate::backoff::Backoff;
+
+use super::{Atomic, assert_failure_ordering, assert_load_ordering, assert_store_ordering};
+
+/// An atomic `Arc` pointer whose replaced values are reclaimed with epochs
+///
+/// `AtomicArc` counts the threads that are loading from a cell, and releases a
+/// value replaced during a load once that count drops to zero. `EpochArc` trades
+/// this reader counting for the epoch-based deferral of `crossbeam-epoch`: it pins
+/// the current thread while it reads the pointer, and defers the release of
+/// replaced values until no pinned thread can still observe them.
+///
+/// The stored `Arc` is boxed, which costs one extra allocation per stored value.
+/// The replaced values may be released later on another thread, so `T` must be
+/// `Send + Sync + 'static`. Tags are not supported.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::Ordering;
+/// use nolock::sync::{Atomic, EpochArc};
+///
+/// let cell = EpochArc::new(1);
+/// let current = cell.load(Ordering::Acquire);
+/// assert!(cell.compare_exchange(current, Arc::new(2), Ordering::AcqRel, Ordering::Acquire).is_ok());
+/// assert_eq!(*cell.load(Ordering::Acquire), 2);
+/// ```
+pub struct EpochArc<T> {
+    inner: EpochAtomic<Arc<T>>,
+}
+
+impl<T: Send + Sync + 'static> EpochArc<T> {
+    pub fn new(val: impl Into<Arc<T>>) -> Self {
+        Self {
+            inner: EpochAtomic::new(val.into()),
+        }
+    }
+
+    /// Borrows the stored value without incrementing its reference count
+    ///
+    /// `load` clones the stored `Arc`, and under read-heavy contention the reference
+    /// count bounces between the cores. A concurrent `store` may replace the value and
+    /// release the stored `Arc` at any time, so the value cannot simply be borrowed.
+    /// The returned `Snapshot` keeps the current thread pinned instead, which defers
+    /// the release of a replaced value until the snapshot is dropped.
+    ///
+    /// Holding snapshots for a long time delays the reclamation of every `EpochArc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    pub fn load_snapshot(&self, order: Ordering) -> Snapshot<'_, T> {
+        assert_load_ordering(order);
+        let guard = epoch::pin();
+        let ptr = self.inner.load(order, &guard).as_raw();
+        Snapshot {
+            _guard: guard,
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the stored value with `new` and returns the replaced value
+    ///
+    /// The box holding the replaced value is destroyed once no pinned thread can
+    /// observe it any more.
+    fn replace(&self, new: Arc<T>, order: Ordering) -> Arc<T> {
+        let guard = epoch::pin();
+        let old = self.inner.swap(Owned::new(new), order, &guard);
+        // SAFETY: the pointer is never null and `old` is no longer reachable from `self`
+        unsafe {
+            let prev = Arc::clone(old.deref());
+            guard.defer_destroy(old);
+            prev
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> Atomic for EpochArc<T> {
+    type Elem = Arc<T>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> Arc<T> {
+        assert_load_ordering(order);
+        let guard = epoch::pin();
+        // SAFETY: the pointer is never null, and the pointee is not destroyed
+        // while `guard` keeps the thread pinned
+        unsafe { Arc::clone(self.inner.load(order, &guard).deref()) }
+    }
+
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<Arc<T>>, order: Ordering) {
+        assert_store_ordering(order);
+        drop(self.replace(val.into(), order))
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer, returning the previously stored pointer
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation.
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<Arc<T>>, order: Ordering) -> Arc<T> {
+        self.replace(val.into(), order)
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer if the stored pointer points to the
+    /// same allocation as `current`.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. `current` is only used for the comparison, and `new` is dropped if
+    /// it is not written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<Arc<T>>,
+        new: impl Into<Arc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<T>, Arc<T>> {
+        assert_failure_ordering(failure);
+        let current: Arc<T> = current.into();
+        let mut new = Owned::new(new.into());
+        let guard = epoch::pin();
+
+        let backoff = Backoff::new();
+        let mut stored = self.inner.load(failure, &guard);
+        loop {
+            // SAFETY: the pointer is never null, and the pointee is not destroyed
+            // while `guard` keeps the thread pinned
+            let stored_arc = unsafe { stored.deref() };
+            if !Arc::ptr_eq(stored_arc, &current) {
+                return Err(Arc::clone(stored_arc))
+            }
+            // retry if only the box was replaced in the meantime
+            match self.inner.compare_exchange(stored, new, success, failure, &guard) {
+                Ok(_) => {
+                    let prev = Arc::clone(stored_arc);
+                    // SAFETY: `stored` is no longer reachable from `self`
+                    unsafe { guard.defer_destroy(stored) };
+                    return Ok(prev)
+                },
+                Err(err) => {
+                    stored = err.current;
+                    new = err.new;
+                },
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer if the stored pointer points to the
+    /// same allocation as `current`.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<Arc<T>>,
+        new: impl Into<Arc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<T>, Arc<T>> {
+        assert_failure_ordering(failure);
+        let current: Arc<T> = current.into();
+        let guard = epoch::pin();
+
+        let stored = self.inner.load(failure, &guard);
+        // SAFETY: the pointer is never null, and the pointee is not destroyed
+        // while `guard` keeps the thread pinned
+        let stored_arc = unsafe { stored.deref() };
+        if !Arc::ptr_eq(stored_arc, &current) {
+            return Err(Arc::clone(stored_arc))
+        }
+        match self.inner.compare_exchange_weak(stored, Owned::new(new.into()), success, failure, &guard) {
+            Ok(_) => {
+                let prev = Arc::clone(stored_arc);
+                // SAFETY: `stored` is no longer reachable from `self`
+                unsafe { guard.defer_destroy(stored) };
+                Ok(prev)
+            },
+            // SAFETY: the pointer is never null and is protected by `guard`
+            Err(err) => Err(Arc::clone(unsafe { err.current.deref() })),
+        }
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer if the stored pointer points to the
+    /// same allocation as `current`, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous value and a new reference to `new`. On failure, which
+    /// may be spurious, returns the current value and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Arc<T>>,
+        new: Arc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Arc<T>, Arc<T>), (Arc<T>, Arc<T>)> {
+        assert_failure_ordering(failure);
+        let current: Arc<T> = current.into();
+        let guard = epoch::pin();
+
+        let stored = self.inner.load(failure, &guard);
+        // SAFETY: the pointer is never null, and the pointee is not destroyed
+        // while `guard` keeps the thread pinned
+        let stored_arc = unsafe { stored.deref() };
+        if !Arc::ptr_eq(stored_arc, &current) {
+            return Err((Arc::clone(stored_arc), new))
+        }
+        match self.inner.compare_exchange_weak(stored, Owned::new(new), success, failure, &guard) {
+            Ok(installed) => {
+                let prev = Arc::clone(stored_arc);
+                // SAFETY: the pointer is never null and is protected by `guard`
+                let installed = Arc::clone(unsafe { installed.deref() });
+                // SAFETY: `stored` is no longer reachable from `self`
+                unsafe { guard.defer_destroy(stored) };
+                Ok((prev, installed))
+            },
+            Err(err) => {
+                // SAFETY: the pointer is never null and is protected by `guard`
+                let actual = Arc::clone(unsafe { err.current.deref() });
+                Err((actual, *err.new.into_box()))
+            },
+        }
+    }
+}
+
+/// A borrowed value of an `EpochArc`, returned by [`EpochArc::load_snapshot`]
+///
+/// The value stays valid while the snapshot is alive, even if it is replaced in
+/// the meantime.
+pub struct Snapshot<'a, T> {
+    // keeps the thread pinned while `ptr` is borrowed
+    _guard: Guard,
+    ptr: *const Arc<T>,
+    _marker: PhantomData<&'a EpochArc<T>>,
+}
+
+impl<T> Snapshot<'_, T> {
+    /// Returns a new reference to the value, which outlives the snapshot
+    pub fn to_arc(&self) -> Arc<T> {
+        Arc::clone(self.as_arc())
+    }
+
+    fn as_arc(&self) -> &Arc<T> {
+        // SAFETY: the pointer is never null, and the pointee is not destroyed
+        // while `_guard` keeps the thread pinned
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> Deref for Snapshot<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.as_arc()
+    }
+}
+
+impl<T> Drop for EpochArc<T> {
+    fn drop(&mut self) {
+        // SAFETY: `&mut self` guarantees that no other thread is accessing the pointer
+        unsafe {
+            let guard = epoch::unprotected();
+            drop(self.inner.load(Ordering::Relaxed, guard).into_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_returns_previous() {
+        let first = Arc::new(1);
+        let cell = EpochArc::new(first.clone());
+
+        let prev = cell.swap(Arc::new(2), Ordering::AcqRel);
+        assert!(Arc::ptr_eq(&prev, &first));
+        assert_eq!(*cell.load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn compare_exchange_compares_allocation() {
+        let first = Arc::new(1);
+        let cell = EpochArc::new(first.clone());
+
+        let actual = cell
+            .compare_exchange(Arc::new(1), Arc::new(2), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert!(Arc::ptr_eq(&actual, &first));
+
+        let prev = cell
+            .compare_exchange(first.clone(), Arc::new(3), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert!(Arc::ptr_eq(&prev, &first));
+        assert_eq!(*cell.load(Ordering::Acquire), 3);
+    }
+
+    #[test]
+    fn concurrent_swap_and_load() {
+        let cell = Arc::new(EpochArc::new(0usize));
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let cell = cell.clone();
+                std::thread::spawn(move || {
+                    for j in 0..1000 {
+                        if j % 2 == 0 {
+                            drop(cell.swap(Arc::new(i * 1000 + j), Ordering::AcqRel));
+                        } else {
+                            assert!(*cell.load(Ordering::Acquire) < 4000);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn snapshot_outlives_replacement() {
+        let first = Arc::new(String::from("first"));
+        let cell = EpochArc::<String>::new(first.clone());
+
+        let snapshot = cell.load_snapshot(Ordering::Acquire);
+        assert_eq!(Arc::strong_count(&first), 2);
+        cell.store(String::from("second"), Ordering::Release);
+        assert_eq!(*snapshot, "first");
+        assert!(Arc::ptr_eq(&snapshot.to_arc(), &first));
+        drop(snapshot);
+
+        assert_eq!(*cell.load_snapshot(Ordering::Acquire), "second");
+    }
+
+    #[test]
+    fn drop_releases_stored_arc() {
+        let value = Arc::new(5);
+        let cell = EpochArc::<i32>::new(value.clone());
+        drop(cell);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
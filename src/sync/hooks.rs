@@ -0,0 +1,104 @@
+//! Instrumentation for making concurrency tests deterministic.
+//!
+//! Only available with `feature = "test-hooks"`.
+
+use std::cell::Cell;
+use std::sync::RwLock;
+
+type CasObserver = Box<dyn Fn(usize, bool) + Send + Sync>;
+
+static CAS_OBSERVER: RwLock<Option<CasObserver>> = RwLock::new(None);
+
+thread_local! {
+    static CAS_ID: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Registers a global observer that is invoked after each `compare_exchange`
+/// and `compare_exchange_weak` performed on an `AtomicArc`.
+///
+/// The observer receives the id set by [`set_cas_id`] on the calling thread
+/// and whether the exchange succeeded. Threads that have not set an id are
+/// not reported. Registering a new observer replaces the previous one.
+pub fn set_cas_observer(f: impl Fn(usize, bool) + Send + Sync + 'static) {
+    let mut observer = CAS_OBSERVER.write()
+        .unwrap_or_else(|err| err.into_inner());
+    *observer = Some(Box::new(f));
+}
+
+/// Removes the global observer
+pub fn clear_cas_observer() {
+    let mut observer = CAS_OBSERVER.write()
+        .unwrap_or_else(|err| err.into_inner());
+    *observer = None;
+}
+
+/// Sets the id reported to the observer for exchanges performed on the current thread
+pub fn set_cas_id(id: usize) {
+    CAS_ID.with(|cell| cell.set(Some(id)));
+}
+
+pub(crate) fn notify_cas(success: bool) {
+    let id = match CAS_ID.with(|cell| cell.get()) {
+        Some(id) => id,
+        None => return
+    };
+    let observer = CAS_OBSERVER.read()
+        .unwrap_or_else(|err| err.into_inner());
+    if let Some(f) = observer.as_ref() {
+        f(id, success)
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use std::sync::{Arc, Mutex, mpsc, atomic::Ordering};
+    use std::thread;
+
+    use super::*;
+    use crate::sync::{Atomic, AtomicArc};
+
+    #[test]
+    fn observer_records_interleaving() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        set_cas_observer(move |id, success| {
+            recorder.lock().unwrap().push((id, success));
+        });
+
+        let cell = Arc::new(AtomicArc::new(1));
+        let (loaded_tx, loaded_rx) = mpsc::channel();
+        let (swapped_tx, swapped_rx) = mpsc::channel();
+
+        // thread 2 loads first, but only attempts its exchange after
+        // thread 1 has replaced the value, so it must fail
+        let t2 = {
+            let cell = cell.clone();
+            thread::spawn(move || {
+                set_cas_id(2);
+                let current = cell.load(Ordering::Acquire);
+                loaded_tx.send(()).unwrap();
+                swapped_rx.recv().unwrap();
+                cell.compare_exchange(current, Arc::new(3), Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+            })
+        };
+        let t1 = {
+            let cell = cell.clone();
+            thread::spawn(move || {
+                set_cas_id(1);
+                loaded_rx.recv().unwrap();
+                let current = cell.load(Ordering::Acquire);
+                let ok = cell.compare_exchange(current, Arc::new(2), Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok();
+                swapped_tx.send(()).unwrap();
+                ok
+            })
+        };
+
+        assert!(t1.join().unwrap());
+        assert!(!t2.join().unwrap());
+        clear_cas_observer();
+
+        assert_eq!(*events.lock().unwrap(), vec![(1, true), (2, false)]);
+    }
+}
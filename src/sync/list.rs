@@ -0,0 +1,124 @@
+//! Singly linked nodes over `TaggedArc` and a read view of their chain.
+//!
+//! A link that carries the mark of [`Marked`](super::Marked), tag bit 0, points to a
+//! logically deleted node, which is skipped when the chain is walked.
+
+use core::{fmt, iter::FusedIterator};
+
+use crate::primitives::Arc;
+
+use super::{marked::MARK, TaggedArc};
+
+/// A node of a singly linked list
+///
+/// The link to the next node is fixed when the node is created, so a chain is only
+/// changed by building new nodes, and a borrowed node keeps the rest of its chain alive.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use nolock::sync::{Marked, TaggedArc};
+/// use nolock::sync::list::Node;
+///
+/// let tail = TaggedArc::from_arc(Arc::new(Node::new(3, None)));
+/// let deleted = Marked::new(Arc::new(Node::new(2, Some(tail)))).mark();
+/// let head = Node::new(1, Some(deleted.into_tagged()));
+///
+/// assert_eq!(head.iter().collect::<Vec<_>>(), [&1, &3]);
+/// ```
+pub struct Node<T> {
+    value: T,
+    next: Option<TaggedArc<Node<T>>>,
+}
+
+impl<T> Node<T> {
+    pub fn new(value: T, next: Option<TaggedArc<Node<T>>>) -> Self {
+        Self {
+            value,
+            next,
+        }
+    }
+
+    /// Creates the node in front of `next`, linked without a tag
+    pub fn push_front(value: T, next: Arc<Node<T>>) -> Self {
+        Self::new(value, Some(TaggedArc::from_arc(next)))
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the link to the next node, including its tag
+    pub fn next(&self) -> Option<&TaggedArc<Node<T>>> {
+        self.next.as_ref()
+    }
+
+    /// Returns an iterator over the values of this node and of the nodes after it,
+    /// skipping the nodes behind marked links
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            node: Some(self),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Node<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Node<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of a chain of nodes, returned by [`Node::iter`]
+pub struct Iter<'a, T> {
+    node: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.node?;
+        let mut next = node.next.as_ref();
+        // skips the logically deleted nodes
+        while let Some(link) = next.filter(|link| link.tag() & MARK != 0) {
+            next = link.next.as_ref();
+        }
+        self.node = next.map(|link| &**link);
+        Some(&node.value)
+    }
+}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+    use crate::sync::Marked;
+
+    #[test]
+    fn iter_skips_marked_nodes() {
+        let tail = Arc::new(Node::new(3, None));
+        let middle = Arc::new(Node::push_front(2, tail.clone()));
+        let plain = Node::push_front(1, middle.clone());
+        assert_eq!(plain.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+
+        // the same chain with the link to the middle node marked
+        let deleted = Marked::new(middle).mark();
+        let head = Node::new(1, Some(deleted.into_tagged()));
+        assert_eq!(head.iter().copied().collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(format!("{:?}", head), "[1, 3]");
+
+        drop((plain, head));
+        assert_eq!(Arc::strong_count(&tail), 1);
+    }
+}
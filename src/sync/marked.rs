@@ -0,0 +1,171 @@
+use core::{fmt, hash::{Hash, Hasher}};
+
+use crate::primitives::Arc;
+
+use super::TaggedArc;
+
+/// The tag bit that marks a pointer
+pub(crate) const MARK: usize = 0b1;
+
+/// A `TaggedArc` whose lowest tag bit is used as a mark
+///
+/// Lock-free lists such as Harris's linked list mark the `next` pointer of a node
+/// to signal that the node is logically deleted. `Marked` keeps the mark in tag
+/// bit 0 and leaves the remaining tag bits to the caller. `T` must be aligned to
+/// at least 2 bytes so that there is a bit to mark.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use nolock::sync::Marked;
+///
+/// let ptr = Marked::new(Arc::new(1u64));
+/// assert!(!ptr.is_marked());
+/// assert!(ptr.mark().is_marked());
+/// ```
+pub struct Marked<T> {
+    ptr: TaggedArc<T>,
+}
+
+impl<T> Marked<T> {
+    /// Creates an unmarked pointer without a tag
+    pub fn new(ptr: Arc<T>) -> Self {
+        Self::from_tagged(TaggedArc::from_arc(ptr))
+    }
+
+    /// Wraps `ptr`, whose tag bit 0 is taken as the mark
+    pub fn from_tagged(ptr: TaggedArc<T>) -> Self {
+        debug_assert!(
+            TaggedArc::<T>::max_tag() & MARK != 0,
+            "the pointer has no unused bit for the mark"
+        );
+        Self {
+            ptr,
+        }
+    }
+
+    pub fn into_tagged(self) -> TaggedArc<T> {
+        self.ptr
+    }
+
+    pub fn as_tagged(&self) -> &TaggedArc<T> {
+        &self.ptr
+    }
+
+    pub fn is_marked(&self) -> bool {
+        self.ptr.tag() & MARK != 0
+    }
+
+    /// Returns a new pointer to the same value with the mark set
+    ///
+    /// The other tag bits are kept.
+    pub fn mark(&self) -> Self {
+        Self {
+            ptr: self.ptr.map_tag(|tag| tag | MARK),
+        }
+    }
+
+    /// Returns a new pointer to the same value with the mark cleared
+    ///
+    /// The other tag bits are kept.
+    pub fn unmark(&self) -> Self {
+        Self {
+            ptr: self.ptr.map_tag(|tag| tag & !MARK),
+        }
+    }
+}
+
+impl<T> Clone for Marked<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: self.ptr.clone(),
+        }
+    }
+}
+
+/// Two `Marked`s are equal if they point to the same allocation and carry the same tag
+impl<T> PartialEq for Marked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T> Eq for Marked<T> {}
+
+impl<T> Hash for Marked<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Marked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Marked")
+            .field("ptr", &self.ptr)
+            .field("marked", &self.is_marked())
+            .finish()
+    }
+}
+
+impl<T> From<Arc<T>> for Marked<T> {
+    fn from(ptr: Arc<T>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<T> From<TaggedArc<T>> for Marked<T> {
+    fn from(ptr: TaggedArc<T>) -> Self {
+        Self::from_tagged(ptr)
+    }
+}
+
+impl<T> From<Marked<T>> for TaggedArc<T> {
+    fn from(ptr: Marked<T>) -> Self {
+        ptr.into_tagged()
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use core::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::sync::{Atomic, AtomicArc};
+
+    #[test]
+    fn mark_and_unmark_are_idempotent() {
+        let arc = Arc::new(9u64);
+        let ptr = Marked::new(arc.clone());
+
+        let marked = ptr.mark();
+        assert!(marked.is_marked());
+        assert_eq!(marked.mark(), marked);
+        assert_eq!(marked.as_tagged().as_raw(), Arc::as_ptr(&arc));
+
+        let unmarked = marked.unmark();
+        assert!(!unmarked.is_marked());
+        assert_eq!(unmarked.unmark(), unmarked);
+        assert_eq!(unmarked, ptr);
+    }
+
+    #[test]
+    fn mark_keeps_other_tag_bits() {
+        let ptr = Marked::from_tagged(TaggedArc::from_arc_with_tag(Arc::new(9u64), 0b110));
+
+        let marked = ptr.mark();
+        assert_eq!(marked.as_tagged().tag(), 0b111);
+        assert_eq!(marked.unmark().as_tagged().tag(), 0b110);
+        assert!(TaggedArc::ptr_eq(marked.as_tagged(), ptr.as_tagged()));
+    }
+
+    #[test]
+    fn marks_stored_pointer() {
+        let cell = AtomicArc::new(9u64);
+        let current = Marked::from(cell.load(Ordering::Acquire));
+
+        cell.compare_exchange(current.clone(), current.mark(), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert!(Marked::from(cell.load(Ordering::Acquire)).is_marked());
+    }
+}
@@ -0,0 +1,37 @@
+//! Counters of failed compare-and-swap operations on `AtomicArc`.
+//!
+//! Only available with `feature = "metrics"`.
+
+use std::cell::Cell;
+
+thread_local! {
+    static GENUINE_FAILURES: Cell<usize> = const { Cell::new(0) };
+    static SPURIOUS_FAILURES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The failed compare-and-swap operations performed on `AtomicArc`s by the current
+/// thread, returned by [`AtomicArc::cas_stats`](super::AtomicArc::cas_stats)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CasStats {
+    /// Exchanges that failed because the stored value differed from `current`
+    pub genuine_failures: usize,
+    /// `compare_exchange_weak` calls that failed although the stored value was `current`
+    pub spurious_failures: usize,
+}
+
+pub(crate) fn cas_stats() -> CasStats {
+    CasStats {
+        genuine_failures: GENUINE_FAILURES.with(Cell::get),
+        spurious_failures: SPURIOUS_FAILURES.with(Cell::get),
+    }
+}
+
+/// Records the outcome of an exchange that expected `current`
+pub(crate) fn record_cas(current: usize, result: &Result<usize, usize>) {
+    let counter = match result {
+        Ok(_) => return,
+        Err(actual) if *actual == current => &SPURIOUS_FAILURES,
+        Err(_) => &GENUINE_FAILURES,
+    };
+    counter.with(|count| count.set(count.get() + 1));
+}
@@ -3,11 +3,74 @@ mod tag;
 #[cfg(feature = "tag")]
 pub use tag::*;
 
+#[cfg(feature = "tag")]
+mod non_null;
+#[cfg(feature = "tag")]
+pub use non_null::*;
+
+#[cfg(feature = "tag")]
+mod marked;
+#[cfg(feature = "tag")]
+pub use marked::*;
+
+#[cfg(feature = "tag")]
+pub mod list;
+
+// `loom::sync::Arc` does not support unsized values
+#[cfg(all(feature = "tag", not(feature = "loom")))]
+mod tag_dyn;
+#[cfg(all(feature = "tag", not(feature = "loom")))]
+pub use tag_dyn::*;
+
+// `loom::sync::Arc` does not support custom allocators
+#[cfg(all(feature = "tag", feature = "allocator_api", not(feature = "loom")))]
+mod tag_in;
+#[cfg(all(feature = "tag", feature = "allocator_api", not(feature = "loom")))]
+pub use tag_in::*;
+
+// `loom` does not provide a `Weak`
+#[cfg(all(feature = "tag", not(feature = "loom")))]
+mod weak;
+#[cfg(all(feature = "tag", not(feature = "loom")))]
+pub use weak::*;
+
+mod reclaim;
+
 mod pointer;
 pub use pointer::*;
 
 mod option;
 pub use option::*;
 
+mod pin;
+pub use pin::*;
+
+mod observed;
+pub use observed::*;
+
+// `crossbeam-epoch` is not modelled by `loom`
+#[cfg(all(feature = "crossbeam-epoch", not(feature = "loom")))]
+mod epoch;
+#[cfg(all(feature = "crossbeam-epoch", not(feature = "loom")))]
+pub use epoch::*;
+
+// double-word atomics are not modelled by `loom`
+#[cfg(all(feature = "dwcas", not(feature = "loom")))]
+mod stamped;
+#[cfg(all(feature = "dwcas", not(feature = "loom")))]
+pub use stamped::*;
+#[cfg(all(feature = "dwcas", not(feature = "loom")))]
+mod slice;
+#[cfg(all(feature = "dwcas", not(feature = "loom")))]
+pub use slice::*;
+
 mod atomic;
-pub use atomic::*;
\ No newline at end of file
+pub use atomic::*;
+
+#[cfg(feature = "test-hooks")]
+pub mod hooks;
+
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "metrics")]
+pub use metrics::CasStats;
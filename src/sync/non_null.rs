@@ -0,0 +1,152 @@
+use core::{fmt, hash::{Hash, Hasher}, ptr::NonNull};
+
+use super::{TaggedArc, compose_tag, decompose_tag, low_bits};
+
+/// Non-owning pointer that uses the lower unused bits for tagging
+///
+/// Unlike [`TaggedArc`], a `TaggedNonNull` does not own a reference to the
+/// pointee, so it is `Copy` and can be used transiently, for example as the
+/// expected value of a compare-and-swap.
+pub struct TaggedNonNull<T> {
+    // data is a tagged pointer
+    data: NonNull<T>,
+}
+
+// the pointee is only accessed through unsafe methods, whose callers must own a
+// reference, so the bounds are those of `TaggedArc`
+unsafe impl<T: Sync + Send> Send for TaggedNonNull<T> {}
+unsafe impl<T: Sync + Send> Sync for TaggedNonNull<T> {}
+
+impl<T> TaggedNonNull<T> {
+    /// Creates a `TaggedNonNull` from `ptr` carrying `tag`
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` does not fit into the unused bits of the pointer.
+    /// In release builds, the bits of `tag` that do not fit are discarded.
+    pub fn new(ptr: NonNull<T>, tag: usize) -> Self {
+        debug_assert!(
+            tag <= low_bits::<T>(),
+            "tag {:#x} does not fit into the unused bits of the pointer", tag
+        );
+        let data = compose_tag::<T>(ptr.as_ptr() as usize, tag);
+        Self {
+            // SAFETY: the pointer part of `data` is taken from a `NonNull`
+            data: unsafe { NonNull::new_unchecked(data as *mut T) },
+        }
+    }
+
+    /// Returns the pointer without the tag
+    pub fn as_ptr(self) -> *mut T {
+        let (ptr, _) = decompose_tag::<T>(self.data.as_ptr() as usize);
+        ptr as *mut T
+    }
+
+    pub fn tag(self) -> usize {
+        let (_, tag) = decompose_tag::<T>(self.data.as_ptr() as usize);
+        tag
+    }
+
+    /// Returns a pointer to the same value carrying `tag`
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` does not fit into the unused bits of the pointer.
+    pub fn with_tag(self, tag: usize) -> Self {
+        // SAFETY: `as_ptr` is the pointer part of a `NonNull`
+        Self::new(unsafe { NonNull::new_unchecked(self.as_ptr()) }, tag)
+    }
+
+    /// Takes over the strong reference at the pointer, keeping the tag
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have been obtained from a `TaggedArc` or `Arc`, and the caller
+    /// must own one strong reference to it, which is handed over to the returned `TaggedArc`.
+    pub unsafe fn into_tagged_arc(self) -> TaggedArc<T> {
+        TaggedArc {
+            data: self.data,
+        }
+    }
+}
+
+impl<T> Clone for TaggedNonNull<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TaggedNonNull<T> {}
+
+/// Two `TaggedNonNull`s are equal if they have the same address and tag
+impl<T> PartialEq for TaggedNonNull<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl<T> Eq for TaggedNonNull<T> {}
+
+impl<T> Hash for TaggedNonNull<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state)
+    }
+}
+
+/// Borrows the pointer of a `TaggedArc` without taking a reference
+impl<T> From<&TaggedArc<T>> for TaggedNonNull<T> {
+    fn from(ptr: &TaggedArc<T>) -> Self {
+        Self {
+            data: ptr.data,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TaggedNonNull<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedNonNull")
+            .field("ptr", &self.as_ptr())
+            .field("tag", &self.tag())
+            .finish()
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+    use crate::primitives::Arc;
+
+    #[test]
+    fn tag_round_trip() {
+        let mut value = 7u64;
+        let raw = NonNull::from(&mut value);
+
+        let ptr = TaggedNonNull::new(raw, 0b101);
+        assert_eq!(ptr.tag(), 0b101);
+        assert_eq!(ptr.as_ptr(), raw.as_ptr());
+
+        let retagged = ptr.with_tag(0b010);
+        assert_eq!(retagged.tag(), 0b010);
+        assert_eq!(retagged.as_ptr(), raw.as_ptr());
+        assert_ne!(ptr, retagged);
+        assert_eq!(unsafe { *retagged.as_ptr() }, 7);
+    }
+
+    #[test]
+    fn borrows_tagged_arc() {
+        let value = Arc::new(3u64);
+        let tagged = TaggedArc::from_arc_with_tag(value.clone(), 0b11);
+
+        let ptr = TaggedNonNull::from(&tagged);
+        assert_eq!(ptr.tag(), 0b11);
+        assert_eq!(ptr.as_ptr() as *const u64, Arc::as_ptr(&value));
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        // hands the reference held by `tagged` over to `owned`
+        core::mem::forget(tagged);
+        let owned = unsafe { ptr.into_tagged_arc() };
+        assert_eq!(owned.tag(), 0b11);
+        drop(owned);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
@@ -0,0 +1,190 @@
+use core::sync::atomic::Ordering;
+
+use super::{Atomic, AtomicArc};
+
+/// An `AtomicArc` that calls a callback after every write
+///
+/// `notify` is called after each `store` and `swap`, and after each `compare_exchange`
+/// that writes the new value, so that observers such as a condition variable or a
+/// waker can be woken once a new value is published. The provided methods of
+/// [`Atomic`], such as `fetch_update`, write through `compare_exchange_weak` and
+/// notify as well.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use nolock::sync::{Atomic, AtomicArc, ObservedArc};
+///
+/// let writes = AtomicUsize::new(0);
+/// let cell = ObservedArc::new(AtomicArc::new(1), || {
+///     writes.fetch_add(1, Ordering::Relaxed);
+/// });
+/// cell.store(Arc::new(2), Ordering::Release);
+/// assert_eq!(writes.load(Ordering::Relaxed), 1);
+/// ```
+pub struct ObservedArc<T, F> {
+    inner: AtomicArc<T>,
+    notify: F,
+}
+
+impl<T, F: Fn()> ObservedArc<T, F> {
+    pub fn new(inner: AtomicArc<T>, notify: F) -> Self {
+        Self {
+            inner,
+            notify,
+        }
+    }
+
+    /// Consumes the wrapper and returns the atomic and the callback
+    pub fn into_parts(self) -> (AtomicArc<T>, F) {
+        (self.inner, self.notify)
+    }
+}
+
+impl<T, F: Fn()> Atomic for ObservedArc<T, F> {
+    type Elem = <AtomicArc<T> as Atomic>::Elem;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> Self::Elem {
+        self.inner.load(order)
+    }
+
+    /// Stores a value into the pointer and calls `notify`
+    ///
+    /// `store` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<Self::Elem>, order: Ordering) {
+        self.inner.store(val, order);
+        (self.notify)()
+    }
+
+    /// Stores a value into the atomic pointer and calls `notify`, returning the previously
+    /// stored value
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation.
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<Self::Elem>, order: Ordering) -> Self::Elem {
+        let prev = self.inner.swap(val, order);
+        (self.notify)();
+        prev
+    }
+
+    /// Stores a value into the atomic pointer if the current value is the same as `current`,
+    /// and calls `notify` if the value was written.
+    ///
+    /// This is [`AtomicArc::compare_exchange`](Atomic::compare_exchange) followed by the
+    /// notification.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<Self::Elem>,
+        new: impl Into<Self::Elem>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Elem, Self::Elem> {
+        let result = self.inner.compare_exchange(current, new, success, failure);
+        if result.is_ok() {
+            (self.notify)()
+        }
+        result
+    }
+
+    /// Stores a value into the atomic pointer if the current value is the same as `current`,
+    /// and calls `notify` if the value was written.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<Self::Elem>,
+        new: impl Into<Self::Elem>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Elem, Self::Elem> {
+        let result = self.inner.compare_exchange_weak(current, new, success, failure);
+        if result.is_ok() {
+            (self.notify)()
+        }
+        result
+    }
+
+    /// Stores a value into the atomic pointer if the current value is the same as `current`,
+    /// handing `new` back if it is not written, and calls `notify` if the value was written.
+    ///
+    /// This is [`AtomicArc::compare_exchange_weak_keep`](Atomic::compare_exchange_weak_keep)
+    /// followed by the notification.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Self::Elem>,
+        new: Self::Elem,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Self::Elem, Self::Elem), (Self::Elem, Self::Elem)> {
+        let result = self.inner.compare_exchange_weak_keep(current, new, success, failure);
+        if result.is_ok() {
+            (self.notify)()
+        }
+        result
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+    use crate::primitives::Arc;
+
+    #[test]
+    fn notifies_on_every_write() {
+        let writes = Cell::new(0);
+        let cell = ObservedArc::new(AtomicArc::new(0), || writes.set(writes.get() + 1));
+
+        for i in 1..=3 {
+            cell.store(Arc::new(i), Ordering::Release);
+        }
+        assert_eq!(writes.get(), 3);
+
+        drop(cell.swap(Arc::new(4), Ordering::AcqRel));
+        assert_eq!(writes.get(), 4);
+
+        let current = cell.load(Ordering::Acquire);
+        assert!(cell.compare_exchange(Arc::new(0), Arc::new(5), Ordering::AcqRel, Ordering::Acquire).is_err());
+        assert_eq!(writes.get(), 4);
+        assert!(cell.compare_exchange(current, Arc::new(5), Ordering::AcqRel, Ordering::Acquire).is_ok());
+        assert_eq!(writes.get(), 5);
+
+        let (inner, _) = cell.into_parts();
+        let value: Arc<i32> = inner.into_arc();
+        assert_eq!(*value, 5);
+    }
+}
@@ -1,211 +1,353 @@
-use std::{intrinsics::transmute, mem::transmute_copy, num::NonZeroUsize, sync::{Arc, atomic::AtomicUsize}, usize};
-use std::sync::atomic::Ordering;
+use core::{marker::PhantomData, mem::ManuallyDrop, sync::atomic::Ordering};
 
-use super::Atomic;
+use crate::primitives::{Arc, AtomicUsize};
+
+use super::{Atomic, assert_failure_ordering, assert_load_ordering, assert_store_ordering};
+use super::reclaim::Reclaim;
 
 #[cfg(feature = "tag")]
 use super::TaggedArc;
 
+/// The pointer type stored in an `AtomicOptionArc`
 #[cfg(feature = "tag")]
-impl<T> Atomic for Option<TaggedArc<T>> {
-    type Target = Self;
-
-    fn load(&self, order: Ordering) -> Self {
-        let ptr = unsafe {
-            let addr = transmute_copy::<Self, AtomicUsize>(self) 
-                .load(order);
-            match TaggedArc::from_usize(addr) {
-                Some(ptr) => ptr,
-                None => return None
-            }
-        };
-        // clone because `load` does not give away ownership
-        Some(TaggedArc::clone(&ptr))
+type Ptr<T> = TaggedArc<T>;
+
+/// The pointer type stored in an `AtomicOptionArc`
+#[cfg(not(feature = "tag"))]
+type Ptr<T> = Arc<T>;
+
+/// Returns the address of `ptr`, which is zero for `None`
+#[cfg(feature = "tag")]
+fn as_data<T>(ptr: &Option<TaggedArc<T>>) -> usize {
+    ptr.as_ref().map_or(0, |ptr| ptr.data.as_ptr() as usize)
+}
+
+/// Returns the address of `ptr`, which is zero for `None`
+#[cfg(not(feature = "tag"))]
+fn as_data<T>(ptr: &Option<Arc<T>>) -> usize {
+    ptr.as_ref().map_or(0, |ptr| Arc::as_ptr(ptr) as usize)
+}
+
+/// Hands the reference held by `ptr` over to the returned address
+#[cfg(feature = "tag")]
+fn into_data<T>(ptr: Option<TaggedArc<T>>) -> usize {
+    ptr.map_or(0, TaggedArc::into_usize)
+}
+
+/// Hands the reference held by `ptr` over to the returned address
+#[cfg(not(feature = "tag"))]
+fn into_data<T>(ptr: Option<Arc<T>>) -> usize {
+    ptr.map_or(0, |ptr| Arc::into_raw(ptr) as usize)
+}
+
+/// # Safety
+///
+/// `data` must be zero or obtained from `into_data`
+#[cfg(feature = "tag")]
+unsafe fn from_data<T>(data: usize) -> Option<TaggedArc<T>> {
+    TaggedArc::from_usize(data)
+}
+
+/// # Safety
+///
+/// `data` must be zero or obtained from `into_data`
+#[cfg(not(feature = "tag"))]
+unsafe fn from_data<T>(data: usize) -> Option<Arc<T>> {
+    if data == 0 {
+        None
+    } else {
+        Some(Arc::from_raw(data as *const T))
     }
+}
+
+/// A nullable `AtomicArc`
+///
+/// `None` is stored as the null address. If `feature = "tag"` is enabled, the
+/// stored pointers are `TaggedArc`s and the tag resides on the unused lower bits.
+///
+/// As with [`AtomicArc`](super::AtomicArc), a value that is replaced while another
+/// thread is loading it is released once no thread is loading from the cell any more.
+pub struct AtomicOptionArc<T> {
+    // data is zero for `None`, or a usize that contains a pointer and a tag if
+    // `feature = "tag"` is enabled
+    data: AtomicUsize,
+    // the values replaced while they were being loaded
+    reclaim: Reclaim,
+    _marker: PhantomData<Option<Arc<T>>>,
+}
 
-    fn store(&self, new: impl Into<Self>, order: Ordering) {
-        let new: Self = new.into();
-        
-        unsafe {
-            let new_data = transmute::<Self, usize>(new);
-            transmute::<&Self, &AtomicUsize>(self)
-                .store(new_data, order)
+unsafe impl<T: Sync + Send> Send for AtomicOptionArc<T> {}
+unsafe impl<T: Sync + Send> Sync for AtomicOptionArc<T> {}
+
+impl<T> AtomicOptionArc<T> {
+    pub fn new(val: impl Into<Option<Ptr<T>>>) -> Self {
+        Self {
+            data: AtomicUsize::new(into_data(val.into())),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         }
     }
 
-    fn swap(&self, new: impl Into<Self::Target>, order: Ordering) -> Self::Target {
-        let new: Self::Target = new.into();
-        
-        unsafe {
-            let new_data = transmute::<Self, usize>(new);
-            let old_data = transmute::<&Self, &AtomicUsize>(self)
-                .swap(new_data, order);
-            TaggedArc::from_usize(old_data) 
+    /// Creates an `AtomicOptionArc` holding `None`
+    ///
+    /// This is a `const fn`, so it can initialize a `static`.
+    #[cfg(not(feature = "loom"))]
+    pub const fn none() -> Self {
+        Self {
+            data: AtomicUsize::new(0),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         }
     }
 
-    fn compare_exchange(&self, current: impl Into<Self::Target>, new: impl Into<Self::Target>, success: Ordering, failure: Ordering) -> Result<Self::Target, Self::Target> {
-        let current: Self::Target = current.into();
-        let new: Self::Target = new.into();
-
-        unsafe {
-            let current = transmute::<Self, usize>(current);
-            let new = transmute::<Self, usize>(new);
-            transmute::<&Self, &AtomicUsize>(self)
-                .compare_exchange(current, new, success, failure)
-                .map(|ok| {
-                    TaggedArc::from_usize(ok)
-                })
-                .map_err(|err| {
-                    TaggedArc::from_usize(err)
-                })
+    /// Creates an `AtomicOptionArc` holding `None`
+    #[cfg(feature = "loom")]
+    pub fn none() -> Self {
+        Self {
+            data: AtomicUsize::new(0),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         }
     }
 
-    fn compare_exchange_weak(&self, current: impl Into<Self::Target>, new: impl Into<Self::Target>, success: Ordering, failure: Ordering) -> Result<Self::Target, Self::Target> {
-        let current: Self::Target = current.into();
-        let new: Self::Target = new.into();
-
-        unsafe {
-            let current = transmute::<Self, usize>(current);
-            let new = transmute::<Self, usize>(new);
-            println!("current: 0x{:x}", current);
-            println!("new: 0x{:x}", new);
-            transmute::<&Self, &AtomicUsize>(self)
-                .compare_exchange_weak(current, new, success, failure)
-                .map(|ok| {
-                    println!("ok: 0x{:x}", ok);
-                    TaggedArc::from_usize(ok).clone()
-                })
-                .map_err(|err| {
-                    println!("err: 0x{:x}", err);
-                    TaggedArc::from_usize(err).clone()
-                })
+    /// Stores `val` if the pointer holds `None`, or returns `val` back otherwise.
+    ///
+    /// The store is `AcqRel` on success, and the load is `Acquire` on failure.
+    pub fn set_if_none(&self, val: Arc<T>) -> Result<(), Arc<T>> {
+        let data = Arc::into_raw(val) as usize;
+        match self.data.compare_exchange(0, data, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => Ok(()),
+            // `val` was not written
+            Err(_) => Err(unsafe { Arc::from_raw(data as *const T) }),
         }
     }
-}
 
-impl<T> Atomic for Option<Arc<T>> {
-    type Target = Self;
-
-    fn load(&self, order: Ordering) -> Self::Target {
-        let addr = unsafe { transmute_copy::<Self, AtomicUsize>(self) }
-            .load(order);
-        let ptr = match NonZeroUsize::new(addr) {
-            Some(data) => {
-                unsafe {
-                    let data: usize = transmute(data);
-                    Arc::from_raw(data as *const T)
-                }
-            },
-            None => return None
-        };
-        // clone because `load` does not give away ownership
-        Some(Arc::clone(&ptr))
+    /// Takes the value out of the pointer, leaving `None` in its place.
+    ///
+    /// `take` takes an `Ordering` argument which describes the memory ordering of this
+    /// operation, like [`swap`](Atomic::swap).
+    pub fn take(&self, order: Ordering) -> Option<Ptr<T>> {
+        self.swap(None, order)
     }
 
-    fn store(&self, new: impl Into<Self::Target>, order: Ordering) {
-        let new: Self::Target = new.into();
+    /// Returns a new reference to the pointer `data`, which is still held by `self`
+    unsafe fn clone_stored(data: usize) -> Option<Ptr<T>> {
+        let ptr = ManuallyDrop::new(from_data::<T>(data));
+        Option::clone(&ptr)
+    }
 
-        unsafe {
-            let new_data = transmute::<Self, usize>(new);
-            transmute::<&Self, &AtomicUsize>(self)
-                .store(new_data, order)
+    /// Releases the reference held by the pointer `data`, if any
+    unsafe fn release(data: usize) {
+        drop(from_data::<T>(data))
+    }
+
+    /// Takes over the reference of the pointer `data`, which was just replaced in `self`
+    ///
+    /// Another thread may still be loading the value, so a new reference is returned
+    /// in that case, and the one of `self` is retired.
+    unsafe fn take_replaced(&self, data: usize) -> Option<Ptr<T>> {
+        if data == 0 || self.reclaim.is_quiescent() {
+            return from_data(data)
         }
+        let ptr = Self::clone_stored(data);
+        self.reclaim.retire(data, Self::release);
+        ptr
     }
 
-    fn swap(&self, new: impl Into<Self::Target>, order: Ordering) -> Self::Target {
-        let new: Self::Target = new.into();
+    /// Runs `cas`, a compare-and-swap on the stored pointer.
+    ///
+    /// On success, the reference of the replaced value is taken over. On failure, a new
+    /// reference to the stored value is returned, which is taken before a concurrent
+    /// store can release the value.
+    fn run_cas(
+        &self,
+        cas: impl FnOnce(&AtomicUsize) -> Result<usize, usize>,
+    ) -> Result<Option<Ptr<T>>, Option<Ptr<T>>> {
+        let result = self.reclaim.protect(|| {
+            // SAFETY: the pointer was found in `self`
+            cas(&self.data).map_err(|err| unsafe { Self::clone_stored(err) })
+        });
+        result.map(|ok| unsafe { self.take_replaced(ok) })
+    }
+}
 
-        unsafe {
-            let new_data = transmute::<Self, usize>(new);
-            let old_data = transmute::<&Self, &AtomicUsize>(self)
-                .swap(new_data, order);
-            match NonZeroUsize::new(old_data) {
-                Some(data) => {
-                    let data: usize = transmute(data);
-                    Some(Arc::from_raw(data as *const T))
-                },
-                None => None
-            }
-        }
+impl<T> Atomic for AtomicOptionArc<T> {
+    type Elem = Option<Ptr<T>>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> Option<Ptr<T>> {
+        assert_load_ordering(order);
+        // no value replaced in the meantime is released before it is cloned
+        self.reclaim.protect(|| {
+            let data = self.data.load(order);
+            // clone because `load` does not give away ownership
+            unsafe { Self::clone_stored(data) }
+        })
     }
 
-    fn compare_exchange(&self, current: impl Into<Self::Target>, new: impl Into<Self::Target>, success: Ordering, failure: Ordering) -> Result<Self::Target, Self::Target> {
-        let current: Self::Target = current.into();
-        let new: Self::Target = new.into();
-
-        unsafe {
-            let current = transmute::<Self, usize>(current);
-            let new = transmute::<Self, usize>(new);
-            transmute::<&Self, &AtomicUsize>(self)
-                .compare_exchange(current, new, success, failure)
-                .map(|ok| {
-                    match NonZeroUsize::new(ok) {
-                        Some(data) => {
-                            let data: usize = transmute(data);
-                            Some(Arc::from_raw(data as *const T))
-                        },
-                        None => None
-                    }
-                })
-                .map_err(|err| {
-                    match NonZeroUsize::new(err) {
-                        Some(data) => {
-                            let data: usize = transmute(data);
-                            Some(Arc::from_raw(data as *const T))
-                        },
-                        None => None
-                    }
-                })
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<Option<Ptr<T>>>, order: Ordering) {
+        assert_store_ordering(order);
+        let old_data = self.data.swap(into_data(val.into()), order);
+        // the previous value must be released once it is replaced
+        if old_data != 0 {
+            unsafe { self.reclaim.retire(old_data, Self::release) }
         }
     }
 
-    fn compare_exchange_weak(&self, current: impl Into<Self::Target>, new: impl Into<Self::Target>, success: Ordering, failure: Ordering) -> Result<Self, Self> {
-        let current: Self::Target = current.into();
-        let new: Self::Target = new.into();
-
-        unsafe {
-            let current = transmute::<Self, usize>(current);
-            let new = transmute::<Self, usize>(new);
-            transmute::<&Self, &AtomicUsize>(self)
-                .compare_exchange_weak(current, new, success, failure)
-                .map(|ok| {
-                    match NonZeroUsize::new(ok) {
-                        Some(data) => {
-                            let data: usize = transmute(data);
-                            Some(Arc::from_raw(data as *const T))
-                        },
-                        None => None
-                    }
-                })
-                .map_err(|err| {
-                    match NonZeroUsize::new(err) {
-                        Some(data) => {
-                            let data: usize = transmute(data);
-                            Some(Arc::from_raw(data as *const T))
-                        },
-                        None => None
-                    }
-                })
+    /// Stores a value into the atomic pointer, returning the previously stored value
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation.
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<Option<Ptr<T>>>, order: Ordering) -> Option<Ptr<T>> {
+        let old_data = self.data.swap(into_data(val.into()), order);
+        unsafe { self.take_replaced(old_data) }
+    }
+
+    /// Stores a value into the atomic pointer if the current value is the same as the
+    /// `current` value. The tag will also be compared if `feature = "tag"` is enabled.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. On success this value is guaranteed to be equal to `current`.
+    /// `current` is only used for the comparison, and `new` is dropped if it is not written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<Option<Ptr<T>>>,
+        new: impl Into<Option<Ptr<T>>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<Ptr<T>>, Option<Ptr<T>>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current = current.into();
+        let new = into_data(new.into());
+
+        let expected = as_data(&current);
+        self.run_cas(|data| data.compare_exchange(expected, new, success, failure))
+            .inspect_err(|_| {
+                // `new` is not stored and must be released
+                drop(unsafe { from_data::<T>(new) });
+            })
+    }
+
+    /// Stores a value into the atomic pointer if the current value is the same as the
+    /// `current` value.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds, which can result in more efficient code on some platforms.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<Option<Ptr<T>>>,
+        new: impl Into<Option<Ptr<T>>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<Ptr<T>>, Option<Ptr<T>>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current = current.into();
+        let new = into_data(new.into());
+
+        let expected = as_data(&current);
+        self.run_cas(|data| data.compare_exchange_weak(expected, new, success, failure))
+            .inspect_err(|_| {
+                // `new` is not stored and must be released
+                drop(unsafe { from_data::<T>(new) });
+            })
+    }
+
+    /// Stores a value into the atomic pointer if the current value is the same as the
+    /// `current` value, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous value and a new reference to `new`. On failure, which
+    /// may be spurious, returns the current value and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Option<Ptr<T>>>,
+        new: Option<Ptr<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Option<Ptr<T>>, Option<Ptr<T>>), (Option<Ptr<T>>, Option<Ptr<T>>)> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current = current.into();
+        let new = into_data(new);
+
+        let expected = as_data(&current);
+        let mut installed = None;
+        let result = self.run_cas(|data| {
+            let result = data.compare_exchange_weak(expected, new, success, failure);
+            if result.is_ok() {
+                // `new` is cloned before a concurrent store can release it
+                installed = Some(unsafe { Self::clone_stored(new) });
+            }
+            result
+        });
+        match result {
+            Ok(prev) => Ok((prev, installed.expect("a stored value must be cloned"))),
+            // `new` is not stored, so its reference is handed back
+            Err(actual) => Err((actual, unsafe { from_data(new) })),
         }
     }
 }
 
+impl<T> Default for AtomicOptionArc<T> {
+    /// Creates an `AtomicOptionArc` that holds `None`
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
 
+impl<T> Drop for AtomicOptionArc<T> {
+    fn drop(&mut self) {
+        // `&mut self` guarantees that no other thread is accessing the pointer
+        let data = self.data.load(Ordering::Relaxed);
+        // SAFETY: `self` owns one strong reference of the stored pointer, if any
+        drop(unsafe { from_data::<T>(data) });
+    }
+}
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "loom")))]
+// the conversions to `Arc` are needed when `feature = "tag"` is enabled
+#[cfg_attr(not(feature = "tag"), allow(clippy::useless_conversion))]
 mod tests {
-    #[allow(unused_imports)]
     use super::*;
-    use std::{mem::size_of};
+    use std::mem::size_of;
     use std::num::NonZeroUsize;
-    use std::sync::Arc;
 
     #[test]
     fn size_of_option_arc() {
         let size = size_of::<Option<Arc<NonZeroUsize>>>();
-        println!("{:?}", &size);
         assert_eq!(size, size_of::<usize>());
     }
 
@@ -213,206 +355,309 @@ mod tests {
     #[test]
     fn size_of_option_tagged() {
         let size = size_of::<Option<TaggedArc<String>>>();
-        println!("{:?}", &size);
         assert_eq!(size, size_of::<usize>());
     }
 
-    #[derive(Debug, Eq, PartialEq)]
-    struct Wrapper {
-        pub inner: NonZeroUsize
-    }
+    #[test]
+    fn test_store_to_none() {
+        let cell = AtomicOptionArc::<i32>::new(None);
+        assert!(cell.load(Ordering::Relaxed).is_none());
 
-    struct ArcWrapper<T>(Arc<T>);
+        cell.store(Some(Arc::new(13).into()), Ordering::Relaxed);
+        assert!(cell.load(Ordering::Relaxed).is_some());
+    }
 
     #[test]
-    fn size_of_option_wrapper_arc() {
-        let size = size_of::<ArcWrapper<&str>>();
-        println!("{:?}", size);
+    fn test_swap() {
+        let cell = AtomicOptionArc::<i32>::new(Some(Arc::new(13).into()));
+        assert!(cell.load(Ordering::Relaxed).is_some());
+
+        drop(cell.swap(None, Ordering::Relaxed));
+        assert!(cell.load(Ordering::Relaxed).is_none());
     }
-    
+
     #[test]
-    fn test_transmute_ref_option() {
-        let opt = Some(Wrapper {
-            inner: NonZeroUsize::new(13).unwrap()
-        });
-        println!("[1] opt was originally: {:?}", &opt);
-        unsafe {
-            transmute::<&Option<Wrapper>, &AtomicUsize>(&&opt)
-                .swap(0, Ordering::Relaxed);
-        }
-        println!("[2] opt becomes {:?}", &opt);
-        assert_eq!(opt, None);
+    fn test_load() {
+        let cell = AtomicOptionArc::<i32>::new(Some(Arc::new(13).into()));
+        let out: Option<Arc<i32>> = cell.load(Ordering::Relaxed).map(Into::into);
+        assert_eq!(out.as_deref(), Some(&13));
+
+        let cell = AtomicOptionArc::<i32>::new(None);
+        assert!(cell.load(Ordering::Relaxed).is_none());
     }
 
     #[test]
-    fn test_transmute_copy_ref_option() {
-        let opt = Some(Wrapper {
-            inner: NonZeroUsize::new(13).unwrap()
-        });
-        println!("[1] opt was originally: {:?}", &opt);
-        unsafe {
-            let ret = transmute_copy::<Option<Wrapper>, AtomicUsize>(&opt)
-                .load(Ordering::Relaxed);
-            println!("[2] returned by load: {:?}", ret);
-        }
+    fn fetch_update_clears_matching() {
+        let is_even = |ptr: &Ptr<i32>| {
+            let ptr: Arc<i32> = ptr.clone().into();
+            *ptr % 2 == 0
+        };
+        let clear_even = |prev: &Option<Ptr<i32>>| match prev {
+            Some(ptr) if is_even(ptr) => Some(None),
+            _ => None,
+        };
+        let value = |ptr: Option<Ptr<i32>>| ptr.map(|ptr| *Arc::from(ptr));
 
-        // unsafe {
-        //     let ret = transmute::<&Option<Wrapper>, &AtomicUsize>(&opt)
-        //         .swap(0, Ordering::Relaxed);
-        //     println!("[2] returned by swap: {:?}", ret);
-        // }
-        println!("[3] opt becomes {:?}", opt);
-        // assert_eq!(opt, None);
+        let cell = AtomicOptionArc::<i32>::new(Some(Arc::new(3).into()));
+        let res = cell.fetch_update(Ordering::AcqRel, Ordering::Acquire, clear_even);
+        assert_eq!(value(res.unwrap_err()), Some(3));
+        assert_eq!(value(cell.load(Ordering::Acquire)), Some(3));
+
+        let cell = AtomicOptionArc::<i32>::new(Some(Arc::new(4).into()));
+        let res = cell.fetch_update(Ordering::AcqRel, Ordering::Acquire, clear_even);
+        assert_eq!(value(res.unwrap()), Some(4));
+        assert!(cell.load(Ordering::Acquire).is_none());
     }
 
     #[test]
-    fn test_store_to_none() {
-        let opt: Option<TaggedArc<i32>> = None;
-        assert_eq!(opt.is_none(), true);
+    fn take_leaves_none() {
+        let value = Arc::new(5);
+        let cell = AtomicOptionArc::<i32>::new(Some(value.clone().into()));
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        let taken = cell.take(Ordering::AcqRel);
+        assert!(taken.is_some());
+        assert!(cell.take(Ordering::AcqRel).is_none());
+        assert!(cell.load(Ordering::Acquire).is_none());
+
+        drop(taken);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
 
-        let ptr = TaggedArc::compose(Arc::new(13), 0);
-        opt.store(ptr, Ordering::Relaxed);
-        assert_eq!(opt.is_none(), false);
+    #[test]
+    fn atomic_option_arc_balances_counts() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicOptionArc::<i32>::new(None);
+
+        let out = cell.compare_exchange(Some(b.clone().into()), Some(a.clone().into()), Ordering::AcqRel, Ordering::Acquire);
+        assert!(out.unwrap_err().is_none());
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 1);
+
+        let out = cell.compare_exchange(None, Some(a.clone().into()), Ordering::AcqRel, Ordering::Acquire);
+        assert!(out.unwrap().is_none());
+        assert_eq!(Arc::strong_count(&a), 2);
+
+        cell.store(Some(b.clone().into()), Ordering::Release);
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 2);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&b), 1);
     }
 
     #[test]
-    fn test_swap() {
-        let opt = Some(TaggedArc::compose(Arc::new(13), 0));
-        assert_eq!(opt.is_none(), false);
+    fn concurrent_swaps_and_takes_balance_counts() {
+        const THREADS: i32 = 4;
+        const ROUNDS: i32 = 100;
+
+        let cell = AtomicOptionArc::<i32>::new(None);
+        let weaks: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let cell = &cell;
+                    s.spawn(move || {
+                        let range = 0..THREADS * ROUNDS;
+                        let mut weaks = Vec::new();
+                        for i in 0..ROUNDS {
+                            // only the cell refers to the swapped in value
+                            let value = Arc::new(t * ROUNDS + i);
+                            weaks.push(Arc::downgrade(&value));
+                            if let Some(prev) = cell.swap(Some(value.into()), Ordering::AcqRel) {
+                                assert!(range.contains(&*prev));
+                            }
+                            if let Some(current) = cell.load(Ordering::Acquire) {
+                                assert!(range.contains(&*current));
+                            }
+                            if let Some(taken) = cell.take(Ordering::AcqRel) {
+                                assert!(range.contains(&*taken));
+                            }
+                        }
+                        weaks
+                    })
+                })
+                .collect();
+            handles.into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
 
-        opt.swap(None, Ordering::Relaxed);
-        assert_eq!(opt.is_none(), true);
+        drop(cell);
+        assert!(weaks.iter().all(|weak| weak.upgrade().is_none()));
     }
 
     #[test]
-    fn test_load() {
-        let o = Some(TaggedArc::compose(Arc::new(13), 0));
-        let out = o.load(Ordering::Relaxed);
-        assert_eq!(out.is_none(), false);
+    fn concurrent_loads_and_stores() {
+        const STORES: i32 = 100;
+
+        let cell = AtomicOptionArc::<i32>::new(None);
+        let weaks = std::thread::scope(|s| {
+            let writer = s.spawn(|| {
+                (1..=STORES)
+                    .map(|i| {
+                        // only the cell refers to the stored value, so replacing it
+                        // releases it unless a load is in progress
+                        let value = Arc::new(i);
+                        let weak = Arc::downgrade(&value);
+                        if i % 2 == 0 {
+                            cell.store(Some(value.into()), Ordering::Release);
+                        } else {
+                            drop(cell.swap(Some(value.into()), Ordering::AcqRel));
+                        }
+                        weak
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            let mut last = 0;
+            while last < STORES {
+                if let Some(current) = cell.load(Ordering::Acquire) {
+                    assert!(*current >= last);
+                    last = *current;
+                }
 
-        let o: Option<TaggedArc<i32>> = None;
-        let out = o.load(Ordering::Relaxed);
-        assert_eq!(out.is_none(), true);
+                // a failed exchange clones the loaded value as well
+                let stale = cell.compare_exchange(Some(Arc::new(-1).into()), None, Ordering::AcqRel, Ordering::Acquire);
+                assert!(stale.is_err());
+            }
+            writer.join().unwrap()
+        });
+
+        drop(cell);
+        assert!(weaks.iter().all(|weak| weak.upgrade().is_none()));
     }
 
     #[test]
-    fn test_() {
-        unimplemented!();
+    fn store_releases_previous_values() {
+        let values: Vec<_> = (0..8).map(Arc::new).collect();
+        let cell = AtomicOptionArc::<i32>::new(None);
+
+        for (i, value) in values.iter().enumerate() {
+            cell.store(Some(value.clone().into()), Ordering::Release);
+            assert_eq!(Arc::strong_count(value), 2);
+            if i > 0 {
+                assert_eq!(Arc::strong_count(&values[i - 1]), 1);
+            }
+        }
+
+        cell.store(None, Ordering::Release);
+        assert!(values.iter().all(|value| Arc::strong_count(value) == 1));
     }
 
     #[test]
-    fn test_taggedarc_compare_exchange_weak() {
-        let arc = Arc::new(13);
-        // let keep_a_copy = arc.clone();
-        println!("[1] arc: {:p}", arc);
-        let ptr = TaggedArc::compose(arc, 0);
-        let old = Some(ptr);
-        // println!("[2] old: {:p}", &old);
-
-        let new_arc = Arc::new(15);
-        println!("[3] new_arc: {:p}", new_arc);
-        let new_ptr = TaggedArc::compose(new_arc, 0);
-        let new = Some(new_ptr);
-        // println!("[4] new: {:p}", &new);
-
-        println!("-------------------");
-        let current = old.load(Ordering::Relaxed);
-        // let out = old.compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Acquire);
-        let out = old.compare_exchange_weak(None, new, Ordering::Acquire, Ordering::Relaxed);
-        // println!("{:?}", &out);
-        // assert!(out.is_err());
-
-        // let out = unsafe {
-        //     let current: usize = transmute(current);
-        //     let new: usize = transmute(new);
-        //     let tmp = transmute::<&_, &AtomicUsize>(&old);    
-        //     tmp.compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Acquire)        
-        // };
-
-        println!("-------------------");
-        let current = old.load(Ordering::Relaxed);
-        
-        let new_arc = Arc::new(15);
-        println!("[5] new_arc: {:p}", new_arc);
-        let new_ptr = TaggedArc::compose(new_arc, 0);
-        let new = Some(new_ptr);        
-        
-        // let new_arc = Arc::new(15);
-        // println!("[5] new_arc: {:p}", new_arc);
-        // let new_ptr = TaggedArc::compose(new_arc, 0);
-        // let new = Some(new_ptr); 
-
-        // let out = old.compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Acquire);
-        let out = old.compare_exchange_weak(None, new, Ordering::AcqRel, Ordering::Acquire);
-        println!("{:?}", out);
-        // assert!(out.is_ok());
-
-        // println!("-------------------");
-        // let out = old.compare_exchange_weak(None, None, Ordering::Acquire, Ordering::Relaxed);
-        // println!("{:?}", out);
-    }    
+    fn none_initializes_static() {
+        static CELLS: [AtomicOptionArc<i32>; 3] = [
+            AtomicOptionArc::none(),
+            AtomicOptionArc::none(),
+            AtomicOptionArc::none(),
+        ];
+        assert!(CELLS.iter().all(|cell| cell.load(Ordering::Acquire).is_none()));
+
+        assert!(CELLS[1].set_if_none(Arc::new(1)).is_ok());
+        let out: Option<Arc<i32>> = CELLS[1].take(Ordering::AcqRel).map(Into::into);
+        assert_eq!(out.as_deref(), Some(&1));
+    }
 
     #[test]
-    fn test_atomic_usize_compare_exchange_weak() {
-        let a = AtomicUsize::new(3);
-        let out = a.compare_exchange_weak(1, 4, Ordering::AcqRel, Ordering::Acquire);
-        println!("{:?}", out);
+    fn set_if_none_only_fills_empty() {
+        let first = Arc::new(1);
+        let cell = AtomicOptionArc::<i32>::none();
+
+        assert!(cell.set_if_none(first.clone()).is_ok());
+        assert_eq!(Arc::strong_count(&first), 2);
+
+        let second = Arc::new(2);
+        let rejected = cell.set_if_none(second.clone()).unwrap_err();
+        assert!(Arc::ptr_eq(&rejected, &second));
+        drop(rejected);
+        assert_eq!(Arc::strong_count(&second), 1);
 
-        let out = a.compare_exchange_weak(3, 4, Ordering::AcqRel, Ordering::Acquire);
-        println!("{:?}", out);
+        let out: Option<Arc<i32>> = cell.load(Ordering::Acquire).map(Into::into);
+        assert!(Arc::ptr_eq(&out.unwrap(), &first));
     }
 
     #[test]
-    fn test_arc_compare_exchange_weak() {
-        let old_arc = Arc::new(13);
-        println!("[1] old arc: {:p}", old_arc);
-        // let old = Some(old_arc);
-
-        unsafe {
-            // let data = transmute::<Arc<i32>, usize>(old_arc)
-                // .load(Ordering::Acquire);
-                // .into_inner();
-                // ;
-
-            let raw = Arc::into_raw(old_arc.clone());
-            println!("{:p}", raw);
-            let data = raw as usize;
-            let data: AtomicUsize = transmute(data);
-            let out = data.load(Ordering::Acquire);
-            println!("data: 0x{:x}", out);
-        }
+    fn default_is_none() {
+        let cell = AtomicOptionArc::<i32>::default();
+        assert!(cell.load(Ordering::Relaxed).is_none());
     }
 
+    #[cfg(feature = "tag")]
     #[test]
-    fn test_arc_load() {
-        let ptr = Arc::new(13);
-        let ptr_addr = format!("{:p}", ptr);
-        println!("ptr_addr: {}", ptr_addr);
-        let opt = Some(ptr);
-        println!("{:p}", &opt);
-        // let out = opt.load(Ordering::Acquire); // this will give wrong result
-        // This will create memory error
-        // let out = unsafe {
-        //     transmute::<_, AtomicUsize>(opt).load(Ordering::Acquire)
-        // };
-
-        let out = unsafe {
-            opt.map(
-                |a| {
-                    let raw = Arc::into_raw(a);
-                    let data = raw as usize;
-                    transmute::<_, AtomicUsize>(data).load(Ordering::Acquire)
-                }
-            ).unwrap()
+    fn swap_none_into_some_and_back() {
+        let value = Arc::new(13);
+        let cell = AtomicOptionArc::<i32>::new(Some(TaggedArc::from_arc_with_tag(value.clone(), 0b1)));
+
+        let prev = cell.swap(None, Ordering::AcqRel).unwrap();
+        assert!(cell.load(Ordering::Acquire).is_none());
+        assert_eq!(prev.tag(), 0b1);
+        assert_eq!(prev.as_raw(), Arc::as_ptr(&value));
+
+        assert!(cell.swap(prev, Ordering::AcqRel).is_none());
+        assert_eq!(cell.load(Ordering::Acquire).unwrap().tag(), 0b1);
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn compare_exchange_weak_back_to_none() {
+        let a = Arc::new(1);
+        let cell = AtomicOptionArc::<i32>::new(Some(a.clone().into()));
+
+        // the weak variant may fail spuriously
+        let prev = loop {
+            if let Ok(prev) = cell.compare_exchange_weak(Some(a.clone().into()), None, Ordering::AcqRel, Ordering::Acquire) {
+                break prev.unwrap()
+            }
         };
-        
-        // match out {
-        //     Some(a) => println!("{:p}", a),
-        //     None => {}
-        // }
+        assert!(cell.load(Ordering::Acquire).is_none());
+        drop(prev);
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+}
 
-        let out_addr = format!("0x{:x}", out);
-        println!("out_addr: {}", out_addr);
-        assert_eq!(ptr_addr, out_addr);
+/// Exercises every operation under Miri, which runs with
+/// `cargo +nightly miri test miri_tests`
+#[cfg(all(test, miri, not(feature = "loom")))]
+mod miri_tests {
+    use super::*;
+    use crate::sync::AtomicArc;
+
+    #[test]
+    fn atomic_option_arc_operations() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicOptionArc::<i32>::new(None);
+
+        cell.store(Some(a.clone().into()), Ordering::Release);
+        assert!(cell.load(Ordering::Acquire).is_some());
+        assert!(cell.swap(Some(b.clone().into()), Ordering::AcqRel).is_some());
+        assert!(cell.compare_exchange(Some(a.clone().into()), None, Ordering::AcqRel, Ordering::Acquire).is_err());
+        assert!(cell.compare_exchange(Some(b.clone().into()), None, Ordering::AcqRel, Ordering::Acquire).is_ok());
+        assert!(cell.take(Ordering::AcqRel).is_none());
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 1);
+    }
+
+    #[test]
+    fn atomic_arc_operations() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_arc(a.clone());
+
+        drop(cell.load(Ordering::Acquire));
+        drop(cell.swap(b.clone(), Ordering::AcqRel));
+        assert!(cell.compare_exchange(a.clone(), a.clone(), Ordering::AcqRel, Ordering::Acquire).is_err());
+        assert!(cell.compare_exchange(b.clone(), a.clone(), Ordering::AcqRel, Ordering::Acquire).is_ok());
+        cell.store(b.clone(), Ordering::Release);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 1);
     }
-}
\ No newline at end of file
+}
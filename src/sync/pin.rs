@@ -0,0 +1,227 @@
+use core::{pin::Pin, sync::atomic::Ordering};
+
+use crate::primitives::Arc;
+
+use super::{Atomic, AtomicArc, assert_failure_ordering};
+
+/// An atomic pointer to a pinned shared value
+///
+/// The value behind an `Arc` never moves, but `AtomicArc` hands out plain `Arc`s,
+/// which allow a value that is not `Unpin` to be moved out with `Arc::try_unwrap`.
+/// `AtomicPinArc` only accepts and returns `Pin<Arc<T>>`, so every value it holds
+/// stays pinned until it is dropped. Tags are not supported.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::Ordering;
+/// use nolock::sync::{Atomic, AtomicPinArc};
+///
+/// let cell = AtomicPinArc::new(Arc::pin(1));
+/// let prev = cell.swap(Arc::pin(2), Ordering::AcqRel);
+/// assert_eq!(*prev, 1);
+/// assert_eq!(*cell.load(Ordering::Acquire), 2);
+/// ```
+pub struct AtomicPinArc<T> {
+    inner: AtomicArc<T>,
+}
+
+impl<T> AtomicPinArc<T> {
+    pub fn new(val: Pin<Arc<T>>) -> Self {
+        Self {
+            inner: AtomicArc::from_arc(Self::unpin(val)),
+        }
+    }
+
+    /// Consumes the atomic and returns the stored value
+    #[allow(clippy::useless_conversion)]
+    pub fn into_inner(self) -> Pin<Arc<T>> {
+        Self::pin(self.inner.into_inner().into())
+    }
+
+    /// Pins an `Arc` that was stored pinned
+    fn pin(ptr: Arc<T>) -> Pin<Arc<T>> {
+        // SAFETY: only pinned values are stored in `inner`
+        unsafe { Pin::new_unchecked(ptr) }
+    }
+
+    /// Unwraps a pinned `Arc` to be stored in `inner`
+    fn unpin(ptr: Pin<Arc<T>>) -> Arc<T> {
+        // SAFETY: the `Arc` is only handed out pinned again
+        unsafe { Pin::into_inner_unchecked(ptr) }
+    }
+}
+
+#[allow(clippy::useless_conversion)]
+impl<T> Atomic for AtomicPinArc<T> {
+    type Elem = Pin<Arc<T>>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> Pin<Arc<T>> {
+        Self::pin(self.inner.load_arc(order))
+    }
+
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<Pin<Arc<T>>>, order: Ordering) {
+        self.inner.store_arc(Self::unpin(val.into()), order)
+    }
+
+    /// Stores a pinned value into the atomic pointer, returning the previously stored value
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation.
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<Pin<Arc<T>>>, order: Ordering) -> Pin<Arc<T>> {
+        let prev = self.inner.swap(Self::unpin(val.into()), order);
+        Self::pin(prev.into())
+    }
+
+    /// Stores a pinned value into the atomic pointer if the stored value is the same
+    /// allocation as `current`.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. `current` is only used for the comparison, and `new` is dropped if
+    /// it is not written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<Pin<Arc<T>>>,
+        new: impl Into<Pin<Arc<T>>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pin<Arc<T>>, Pin<Arc<T>>> {
+        assert_failure_ordering(failure);
+        let current = Self::unpin(current.into());
+        self.inner
+            .compare_exchange_ref(&current, Self::unpin(new.into()), success, failure)
+            .map(|prev| Self::pin(prev.into()))
+            .map_err(|actual| Self::pin(actual.into()))
+    }
+
+    /// Stores a pinned value into the atomic pointer if the stored value is the same
+    /// allocation as `current`.
+    ///
+    /// This never fails spuriously and is the same as [`compare_exchange`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<Pin<Arc<T>>>,
+        new: impl Into<Pin<Arc<T>>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Pin<Arc<T>>, Pin<Arc<T>>> {
+        self.compare_exchange(current, new, success, failure)
+    }
+
+    /// Stores a pinned value into the atomic pointer if the stored value is the same
+    /// allocation as `current`, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous value and a new reference to `new`. On failure,
+    /// which may be spurious, returns the current value and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Pin<Arc<T>>>,
+        new: Pin<Arc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Pin<Arc<T>>, Pin<Arc<T>>), (Pin<Arc<T>>, Pin<Arc<T>>)> {
+        let current = Self::unpin(current.into());
+        self.inner
+            .compare_exchange_weak_keep(current, Self::unpin(new).into(), success, failure)
+            .map(|(prev, new)| (Self::pin(prev.into()), Self::pin(new.into())))
+            .map_err(|(actual, new)| (Self::pin(actual.into()), Self::pin(new.into())))
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use core::marker::PhantomPinned;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Node {
+        value: u32,
+        next: Option<Pin<Arc<Node>>>,
+        _pinned: PhantomPinned,
+    }
+
+    fn node(value: u32, next: Option<Pin<Arc<Node>>>) -> Pin<Arc<Node>> {
+        Arc::pin(Node {
+            value,
+            next,
+            _pinned: PhantomPinned,
+        })
+    }
+
+    fn addr(node: &Node) -> *const Node {
+        node
+    }
+
+    #[test]
+    fn pinned_graph_keeps_addresses() {
+        let tail = node(2, None);
+        let head = node(1, Some(tail.clone()));
+        let cell = AtomicPinArc::new(head.clone());
+
+        let first = cell.load(Ordering::Acquire);
+        let second = cell.load(Ordering::Acquire);
+        assert_eq!(addr(&first), addr(&head));
+        assert_eq!(addr(&second), addr(&head));
+        assert_eq!(addr(first.next.as_ref().unwrap()), addr(&tail));
+
+        let prev = cell.swap(node(3, Some(tail.clone())), Ordering::AcqRel);
+        assert_eq!(addr(&prev), addr(&head));
+        assert_eq!(prev.value, 1);
+
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.value, 3);
+        assert_eq!(addr(current.next.as_ref().unwrap()), addr(&tail));
+    }
+
+    #[test]
+    fn compare_exchange_compares_allocation() {
+        let first = node(1, None);
+        let cell = AtomicPinArc::new(first.clone());
+
+        let actual = cell
+            .compare_exchange(node(1, None), node(2, None), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert_eq!(addr(&actual), addr(&first));
+
+        let prev = cell
+            .compare_exchange(first.clone(), node(2, None), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert_eq!(addr(&prev), addr(&first));
+        assert_eq!(cell.into_inner().value, 2);
+    }
+}
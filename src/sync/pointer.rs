@@ -1,19 +1,55 @@
-use std::{mem::transmute, mem::transmute_copy, ptr::NonNull, sync::{Arc, atomic::{AtomicUsize, Ordering}}};
-use std::num::NonZeroUsize;
+use core::{fmt, marker::PhantomData, mem::ManuallyDrop, ptr, sync::atomic::Ordering};
 
-use super::{Atomic};
+use crate::backoff::Backoff;
+use crate::primitives::{Arc, AtomicUsize};
+#[cfg(not(feature = "loom"))]
+use crate::primitives::Weak;
+#[cfg(not(feature = "loom"))]
+use alloc::boxed::Box;
+
+use super::{Atomic, assert_failure_ordering, assert_load_ordering, assert_store_ordering, failure_ordering};
+use super::reclaim::Reclaim;
 
 #[cfg(feature = "tag")]
-use super::TaggedArc;
+use super::{TaggedArc, compose_tag, low_bits};
 
 /// A wrapper that change all API to only accept and return `Arc` and allows tagging
 ///
 /// If `feature = "tag"` is enabled, the tag will be stored in the unused lower bits 
 /// of the pointer address.
+///
+/// A load reads the stored pointer before it takes a new reference. A value that is
+/// replaced while another thread is loading it is therefore not released right away,
+/// but once no thread is loading from the cell any more. Until then it is kept by the
+/// cell, at the latest until the cell is dropped.
+///
+/// There is no load that borrows the stored value without a new reference. Such a
+/// borrow would have to defer the release of every value replaced while it is held,
+/// not only during the few instructions of a load, and every store would then leave
+/// its value behind until the borrows end. `EpochArc::load_snapshot` provides that
+/// borrow with the epoch-based reclamation of `feature = "crossbeam-epoch"`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::Ordering;
+/// use nolock::sync::{Atomic, AtomicArc};
+///
+/// let cell = AtomicArc::new(1);
+/// let old: Arc<i32> = cell.swap(Arc::new(2), Ordering::AcqRel).into();
+/// assert_eq!(*old, 1);
+///
+/// let current: Arc<i32> = cell.load(Ordering::Acquire).into();
+/// assert_eq!(*current, 2);
+/// ```
 pub struct AtomicArc<T> {
     // data is a usize that contains a pointer and a tag if `feature = "tag"`is enabled. 
     // The tag resides on the unused lower bits.
-    data: NonNull<T>,
+    data: AtomicUsize,
+    // the values replaced while they were being loaded
+    reclaim: Reclaim,
+    _marker: PhantomData<Arc<T>>,
 }
 
 unsafe impl<T: Sync + Send> Send for AtomicArc<T> {}
@@ -26,424 +62,2841 @@ impl<T> AtomicArc<T> {
     }
 
     pub fn from_arc(val: Arc<T>) -> Self {
-        let raw = Arc::into_raw(val) as *mut T;
-        let data = unsafe { NonNull::new_unchecked(raw)};
+        let data = Arc::into_raw(val) as usize;
         Self {
-            data,
+            data: AtomicUsize::new(data),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         }
     }
 
+    /// Creates a new `AtomicArc` holding a value that may refer to itself through the
+    /// `Weak` passed to `f`. See [`Arc::new_cyclic`](alloc::sync::Arc::new_cyclic).
+    #[cfg(not(feature = "loom"))]
+    pub fn new_cyclic<F>(f: F) -> Self
+    where
+        F: FnOnce(&Weak<T>) -> T,
+    {
+        Self::from_arc(Arc::new_cyclic(f))
+    }
+
     #[cfg(feature = "tag")]
     pub fn from_tagged(tagged: TaggedArc<T>) -> Self {
-        let data = tagged.data;
+        // the strong reference is handed over to `self`
+        let data = tagged.into_usize();
         Self {
-            data,
+            data: AtomicUsize::new(data),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         }
     }
 
     // Only API that expose Arc should be public
+    /// # Safety
+    /// 
+    /// `val` must be the address of a valid `Arc` pointer
     pub unsafe fn from_usize(val: usize) -> Option<Self> {
-        let data = NonZeroUsize::new(val)?;
+        if val == 0 {
+            return None
+        }
         let ret = Self {
-            data: transmute(data)
+            data: AtomicUsize::new(val),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         };
         Some(ret)
     }
 
-    pub fn get_mut() {
-        unimplemented!()
+    /// Returns a reference to the underlying atomic storage.
+    ///
+    /// The storage holds the address of the `Arc` and, if `feature = "tag"` is enabled,
+    /// the tag in its unused lower bits. This allows atomic operations on the tag that
+    /// are not provided by `AtomicArc`, such as `fetch_add`.
+    ///
+    /// # Safety
+    ///
+    /// Any operation through the returned reference must leave the pointer bits
+    /// unchanged, which includes carrying out of the tag bits. Changing the pointer
+    /// bits is undefined behavior, as `self` then releases a reference it does not own.
+    pub unsafe fn as_atomic_usize(&self) -> &AtomicUsize {
+        &self.data
     }
-}
-
-#[cfg(feature = "tag")]
-impl<T> Atomic for AtomicArc<T> {
-    type Target = TaggedArc<T>;
 
-    /// Loads a value from the atomic pointer.
+    /// Returns a mutable reference to the underlying pointer.
     ///
-    /// `load` takes an `Ordering` argument which describes 
-    /// the memory ordering of this operation. 
-    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    /// This is safe because the mutable reference guarantees that no other threads are
+    /// concurrently accessing the atomic data.
     ///
-    /// # Panics
-    /// 
-    /// Panics if `order` is `Release` or `AcqRel`.
-    fn load(&self, order: Ordering) -> TaggedArc<T> {
-        let ptr = unsafe {
-            let addr = transmute_copy::<NonNull<T>, AtomicUsize>(&self.data)
-                .load(order);
-            TaggedArc::from_usize(addr)
-                .expect("AtomicArc pointer must be non-zero")
-        };
-        // clone because `load` does not give away ownership
-        TaggedArc::clone(&ptr)
+    /// Not available with `feature = "loom"`, whose atomics cannot be borrowed mutably.
+    #[cfg(all(feature = "tag", not(feature = "loom")))]
+    pub fn get_mut(&mut self) -> &mut TaggedArc<T> {
+        // SAFETY: `TaggedArc` is a transparent wrapper of the non-zero tagged pointer
+        // stored in `self`
+        unsafe { &mut *(self.data.get_mut() as *mut usize as *mut TaggedArc<T>) }
     }
 
-    /// Stores a value into the pointer
+    /// Returns a mutable handle to the underlying pointer.
     ///
-    /// `store` takes an `Ordering` argument which describes 
-    /// the memory ordering of this operation. 
-    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    /// This is safe because the mutable reference guarantees that no other threads are
+    /// concurrently accessing the atomic data. `self` only stores the raw pointer, so the
+    /// handle takes over the reference of `self` as an `Arc`, which is written back when
+    /// the handle is dropped. The strong count is not changed, so
+    /// [`Arc::get_mut`](alloc::sync::Arc::get_mut) succeeds through the handle if `self`
+    /// holds the only reference.
+    #[cfg(not(feature = "tag"))]
+    pub fn get_mut(&mut self) -> ArcMut<'_, T> {
+        // `&mut self` guarantees that no other thread is accessing the pointer
+        let data = self.data.load(Ordering::Relaxed);
+        ArcMut {
+            // the reference of `self` is returned when the handle is dropped
+            ptr: ManuallyDrop::new(unsafe { Arc::from_raw(data as *const T) }),
+            data: &mut self.data,
+        }
+    }
+
+    /// Returns a mutable reference to the stored value if no other `Arc` or `Weak`
+    /// points to it, in the same way as [`Arc::get_mut`](alloc::sync::Arc::get_mut).
     ///
-    /// # Panics
-    /// 
-    /// Panics if `order` is `Acquire` or `AcqRel`.
-    fn store(&self, val: impl Into<TaggedArc<T>>, order: Ordering) {
-        let ptr: TaggedArc<T> = val.into();
-        let new_data = ptr.into_usize();
-        // self.data.store(new_data, order)
+    /// Returns `None` if the value is shared.
+    pub fn get_mut_inner(&mut self) -> Option<&mut T> {
+        // `&mut self` guarantees that no other thread is accessing the pointer
+        let ptr = self.load_ptr(Ordering::Relaxed);
+        // the stored reference still belongs to `self`
+        let mut arc = ManuallyDrop::new(unsafe { Arc::from_raw(ptr) });
+        // SAFETY: the value is kept alive by the reference held by `self`, and
+        // `&mut self` keeps it from being replaced or shared while it is borrowed
+        Arc::get_mut(&mut arc).map(|val| unsafe { &mut *(val as *mut T) })
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    ///
+    /// This is safe because passing `self` by value guarantees that no other threads are
+    /// concurrently accessing the atomic data. The strong reference held by `self` is handed
+    /// over to the returned pointer, so the strong count is not changed.
+    #[cfg(feature = "tag")]
+    pub fn into_inner(self) -> TaggedArc<T> {
+        // `self` must not be dropped as its reference is handed over
+        let this = ManuallyDrop::new(self);
         unsafe {
-            transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .store(new_data, order)
+            drop(ptr::read(&this.reclaim));
+            let data = ptr::read(&this.data).into_inner();
+            TaggedArc::from_usize(data)
+                .expect("AtomicArc pointer must be non-zero")
         }
     }
 
-    /// Stores a `TaggedArc` pointer into the atomic pointer, returning the previously stored pointer
+    /// Consumes the atomic and returns the contained value.
     ///
-    /// swap takes an `Ordering` argument which describes the memory ordering of this operation. 
-    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this 
-    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.            
-    fn swap(&self, val: impl Into<TaggedArc<T>>, order: Ordering) -> TaggedArc<T> {
-        let ptr: TaggedArc<T> = val.into();
-        let new_data = ptr.into_usize();
-        // let old_data = self.data.swap(new_data, order);
-        
-        // SAFETY: only raw Arc pointers will be stored in the pointer
+    /// This is safe because passing `self` by value guarantees that no other threads are
+    /// concurrently accessing the atomic data. The strong reference held by `self` is handed
+    /// over to the returned pointer, so the strong count is not changed.
+    #[cfg(not(feature = "tag"))]
+    pub fn into_inner(self) -> Arc<T> {
+        // `self` must not be dropped as its reference is handed over
+        let this = ManuallyDrop::new(self);
         unsafe {
-            let old_data = transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .swap(new_data, order);
-            TaggedArc::from_usize(old_data)
-                .expect("AtomicArc pointer must be non-zero")
+            drop(ptr::read(&this.reclaim));
+            let data = ptr::read(&this.data).into_inner();
+            Arc::from_raw(data as *const T)
         }
-    }   
+    }
 
-    /// Stores a `TaggedArc` pointer into the if the current value is the same as the `current` value.
-    /// The tag will also be compared.
+    /// Consumes the atomic and returns the contained value as a plain `Arc`,
+    /// discarding the tag.
     ///
-    /// The return value is a result indicating whether the new value was written and containing
-    /// the previous value. On success this value is guaranteed to be equal to `current`.
+    /// Unlike [`into_inner`](AtomicArc::into_inner), the return type is the same
+    /// whether `feature = "tag"` is enabled or not.
+    #[cfg(feature = "tag")]
+    pub fn into_arc(self) -> Arc<T> {
+        self.into_inner().into_arc()
+    }
+
+    /// Consumes the atomic and returns the contained value as a plain `Arc`,
+    /// discarding the tag.
     ///
-    /// `compare_exchange` takes two [`Ordering`] arguments to describe the memory
-    /// ordering of this operation. `success` describes the required ordering for the
-    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
-    /// `failure` describes the required ordering for the load operation that takes place when
-    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
-    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
-    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
-    /// and must be equivalent to or weaker than the success ordering.
-    fn compare_exchange(
-        &self,
-        current: impl Into<TaggedArc<T>>,
-        new: impl Into<TaggedArc<T>>,
-        success: Ordering,
-        failure: Ordering,
-    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
-        let current: TaggedArc<T> = current.into();
-        let current = current.into_usize();
-        let new: TaggedArc<T> = new.into();
-        let new = new.into_usize();
+    /// Unlike [`into_inner`](AtomicArc::into_inner), the return type is the same
+    /// whether `feature = "tag"` is enabled or not.
+    #[cfg(not(feature = "tag"))]
+    pub fn into_arc(self) -> Arc<T> {
+        self.into_inner()
+    }
 
-        // SAFETY: The stored address must come from a valid Arc pointer
-        unsafe {
-            transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .compare_exchange(current, new, success, failure)
-                .map(|ok| {
-                    TaggedArc::from_usize(ok)
-                        .expect("AtomicArc pointer must be non-zero")
-                })
-                .map_err(|err| {
-                    TaggedArc::from_usize(err)
-                        .expect("AtomicArc pointer must be non-zero")
-                })
+    /// Consumes the atomic and returns the stored pointer.
+    ///
+    /// The strong reference held by `self` is handed over to the returned pointer, which
+    /// must be passed to [`from_raw`](AtomicArc::from_raw) to be released. If
+    /// `feature = "tag"` is enabled, the tag is kept in the unused lower bits of the
+    /// returned pointer, so it must not be dereferenced unless the tag is zero.
+    #[must_use = "the reference is leaked if the returned pointer is discarded"]
+    pub fn into_raw(self) -> *const T {
+        // `self` must not be dropped as its reference is handed over
+        let this = ManuallyDrop::new(self);
+        drop(unsafe { ptr::read(&this.reclaim) });
+        let data = unsafe { ptr::read(&this.data) }.into_inner();
+        data as *const T
+    }
+
+    /// Creates an `AtomicArc` from a pointer returned by [`into_raw`](AtomicArc::into_raw),
+    /// taking over its strong reference and keeping its tag.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be obtained from [`AtomicArc::into_raw`] or `Arc::into_raw`, and
+    /// must not be used to release the reference again.
+    pub unsafe fn from_raw(raw: *const T) -> Self {
+        Self {
+            data: AtomicUsize::new(raw as usize),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
         }
     }
 
-    /// Stores an `Arc` pointer into the atomic pointer if the current value is the same as the `current` value.
+    /// Stores `new` into the pointer if the current value is the same as `current`,
+    /// returning whether the value was written.
     ///
-    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
-    /// comparison succeeds, which can result in more efficient code on some platforms. The
-    /// return value is a result indicating whether the new value was written and containing the
-    /// previous value.
+    /// This is [`compare_exchange`] for callers that do not need the previous value.
+    /// On success the replaced value is released, and on failure `new` and the loaded
+    /// value are released, so the caller has nothing left to reclaim in either case.
     ///
-    /// `compare_exchange_weak` takes two [`Ordering`] arguments to describe the memory
-    /// ordering of this operation. `success` describes the required ordering for the
-    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
-    /// `failure` describes the required ordering for the load operation that takes place when
-    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
-    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
-    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
-    /// and must be equivalent to or weaker than the success ordering.
-    fn compare_exchange_weak(
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    pub fn compare_exchange_drop(
         &self,
-        current: impl Into<TaggedArc<T>>,
-        new: impl Into<TaggedArc<T>>,
+        current: impl Into<<Self as Atomic>::Elem>,
+        new: impl Into<<Self as Atomic>::Elem>,
         success: Ordering,
         failure: Ordering,
-    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
-        let current: TaggedArc<T> = current.into();
-        let current = current.into_usize();
-        let new: TaggedArc<T> = new.into();
-        let new = new.into_usize();
-        // self.data.compare_exchange_weak(current, new, success, failure)
-        //     .map(|success| {
-        //         unsafe{ TaggedArc::from_usize(success) }
-        //     })
-        //     .map_err(|failure| {
-        //         unsafe{ TaggedArc::from_usize(failure) }
-        //     })
-
-        unsafe {
-            transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .compare_exchange_weak(current, new, success, failure)
-                .map(|ok| {
-                    TaggedArc::from_usize(ok)
-                        .expect("AtomicArc pointer must be non-zero")
-                })
-                .map_err(|err| {
-                    TaggedArc::from_usize(err)
-                        .expect("AtomicArc pointer must be non-zero")
-                })
-        }
+    ) -> bool {
+        self.compare_exchange(current, new, success, failure)
+            .is_ok()
     }
 
-    // /// Fetches the value, and applies a function to it that returns an optional
-    // /// new value. Returns a `Result` of `Ok(previous_value)` if the function
-    // /// returned `Some(_)`, else `Err(previous_value)`.
-    // ///
-    // /// Note: This may call the function multiple times if the value has been
-    // /// changed from other threads in the meantime, as long as the function
-    // /// returns `Some(_)`, but the function will have been applied only once to
-    // /// the stored value.
-    // ///
-    // /// Note: This does not protect the program from the ABA problem. 
-    // ///
-    // /// `fetch_update` takes two [`Ordering`] arguments to describe the memory
-    // /// ordering of this operation. The first describes the required ordering for
-    // /// when the operation finally succeeds while the second describes the
-    // /// required ordering for loads. These correspond to the success and failure
-    // /// orderings of [`AtomicPtr::compare_exchange`] respectively.
-    // ///
-    // /// Using [`Acquire`] as success ordering makes the store part of this
-    // /// operation [`Relaxed`], and using [`Release`] makes the final successful
-    // /// load [`Relaxed`]. The (failed) load ordering can only be [`SeqCst`],
-    // /// [`Acquire`] or [`Relaxed`] and must be equivalent to or weaker than the
-    // /// success ordering.
-    // fn fetch_update<F>(
-    //     &self,
-    //     set_order: Ordering,
-    //     fetch_order: Ordering,
-    //     mut f: F
-    // ) -> Result<TaggedArc<T>, TaggedArc<T>>
-    // where 
-    //     F: FnMut(&TaggedArc<T>) -> Option<TaggedArc<T>>
-    // {
-    //     let mut prev = self.load(fetch_order);
-    //     while let Some(next) = f(&prev) {
-    //         match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
-    //             x @ Ok(_) => return x,
-    //             Err(next_prev) => prev = next_prev,
-    //         }
-    //     }
-    //     Err(prev)
-    // }
-}
-
-#[cfg(not(feature = "tag"))]
-impl<T> Atomic for AtomicArc<T> {
-    type Target = Arc<T>;
-
-    /// Loads a value from the atomic pointer.
+    /// Stores `new` into the pointer if the current value is the same as `current`,
+    /// returning whether the value was written.
     ///
-    /// `load` takes an `Ordering` argument which describes 
-    /// the memory ordering of this operation. 
-    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    /// This is [`compare_exchange`] for callers that do not need the previous value.
+    /// On success the replaced value is released, and on failure `new` is released,
+    /// so no reference is leaked in either case.
     ///
-    /// # Panics
-    /// 
-    /// Panics if `order` is `Release` or `AcqRel`.
-    fn load(&self, order: Ordering) -> Arc<T> {
-        let ptr = unsafe {
-            let addr = transmute_copy::<NonNull<T>, AtomicUsize>(&self.data)
-                .load(order);
-            Arc::from_raw(addr as *const T)
-        };
-        // clone because `load` doesn't give away ownership
-        Arc::clone(&ptr)
+    /// `order` is the ordering of the successful exchange. The failure ordering is the
+    /// strongest one allowed for `order`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    pub fn compare_and_set(
+        &self,
+        current: impl Into<<Self as Atomic>::Elem>,
+        new: impl Into<<Self as Atomic>::Elem>,
+        order: Ordering,
+    ) -> bool {
+        self.compare_exchange_drop(current, new, order, failure_ordering(order))
     }
 
-    /// Stores a value into the pointer
-    ///
-    /// `store` takes an `Ordering` argument which describes 
-    /// the memory ordering of this operation. 
-    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    /// Loads the stored value for following the pointer.
     ///
-    /// # Panics
-    /// 
-    /// Panics if `order` is `Acquire` or `AcqRel`.
-    fn store(&self, val: impl Into<Arc<T>>, order: Ordering) {
-        let ptr: Arc<T> = val.into();
-        let new_data = Arc::into_raw(ptr) as usize;
-        unsafe {
-            transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .store(new_data, order)
-        }
+    /// Only the loads that depend on the returned pointer need to be ordered after this
+    /// load, as with the C++ `memory_order_consume`. Rust has no such ordering, so this
+    /// is currently the same as `load(Ordering::Acquire)`, in the same way as
+    /// `crossbeam_epoch::Atomic::load_consume`. Pointer-chasing code can use it to state
+    /// the weaker requirement, which leaves room to implement it more cheaply later.
+    pub fn load_consume(&self) -> <Self as Atomic>::Elem {
+        self.load(Ordering::Acquire)
     }
 
-    /// Stores a `Arc` pointer into the atomic pointer, returning the previously stored pointer
+    /// Replaces the stored value with the value returned by `f` in a read-copy-update loop.
     ///
-    /// swap takes an `Ordering` argument which describes the memory ordering of this operation. 
-    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this 
-    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
-    fn swap(&self, val: impl Into<Arc<T>>, order: Ordering) -> Arc<T> {
-        let ptr: Arc<T> = val.into();
-        let new_data = Arc::into_raw(ptr) as usize;
-        // SAFETY: only raw Arc pointers will be stored in the pointer
-        unsafe {
-            let old_data = transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .swap(new_data, order);
-            Arc::from_raw(old_data as *const T)
+    /// The current value is loaded and passed to `f`, and the returned value is installed
+    /// with `compare_exchange_weak` in a new `Arc`. If the value was changed by another
+    /// thread in the meantime, the process is repeated on the new value. `f` may
+    /// therefore run more than once and should be free of side effects.
+    ///
+    /// The tag is carried over to the new value if `feature = "tag"` is enabled.
+    pub fn rcu<F>(&self, f: F)
+    where
+        F: Fn(&T) -> T
+    {
+        let backoff = Backoff::new();
+        let mut current = self.load(Ordering::Acquire);
+        loop {
+            let new = Self::with_tag_of(Arc::new(f(&*current)), &current);
+            match self.compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+            backoff.snooze();
         }
     }
 
-    /// Stores a `Arc` pointer into the if the current value is the same as the `current` value.
+    /// Stores `new` if `f` returns `true` for the stored value, returning the previous value.
     ///
-    /// The return value is a result indicating whether the new value was written and containing
-    /// the previous value. On success this value is guaranteed to be equal to `current`.
+    /// The current value is loaded and passed to `f`. If `f` returns `false`, nothing is
+    /// stored and the current value is returned as the error. Otherwise `new` is installed
+    /// with `compare_exchange_weak`, and if the value was changed by another thread in the
+    /// meantime, `f` runs again on the new value.
     ///
-    /// `compare_exchange` takes two [`Ordering`] arguments to describe the memory
-    /// ordering of this operation. `success` describes the required ordering for the
-    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
-    /// `failure` describes the required ordering for the load operation that takes place when
-    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
-    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
-    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
-    /// and must be equivalent to or weaker than the success ordering.
-    fn compare_exchange(
-        &self,
-        current: impl Into<Arc<T>>,
-        new: impl Into<Arc<T>>,
-        success: Ordering,
-        failure: Ordering,
-    ) -> Result<Arc<T>, Arc<T>> {
-        let current: Arc<T> = current.into();
-        let current = Arc::into_raw(current) as usize;
-        let new: Arc<T> = new.into();
-        let new = Arc::into_raw(new) as usize;
-
-        unsafe {
-            transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .compare_exchange(current, new, success, failure)
-                .map(|ok| {
-                    Arc::from_raw(ok as *const T)
-                })
-                .map_err(|err| {
-                    Arc::from_raw(err as *const T)
-                })
+    /// The tag is carried over to `new` if `feature = "tag"` is enabled.
+    ///
+    /// `swap_if` takes an `Ordering` argument which describes the memory ordering of the
+    /// store when it succeeds. The loads use the strongest failure ordering allowed for `order`.
+    #[allow(clippy::useless_conversion)]
+    pub fn swap_if<F>(&self, new: Arc<T>, f: F, order: Ordering) -> Result<Arc<T>, Arc<T>>
+    where
+        F: Fn(&T) -> bool
+    {
+        let fetch_order = failure_ordering(order);
+        let backoff = Backoff::new();
+        let mut current = self.load(fetch_order);
+        loop {
+            if !f(&*current) {
+                return Err(current.into())
+            }
+            let next = Self::with_tag_of(new.clone(), &current);
+            match self.compare_exchange_weak(current, next, order, fetch_order) {
+                Ok(prev) => return Ok(prev.into()),
+                Err(actual) => current = actual,
+            }
+            backoff.snooze();
         }
     }
 
-    /// Stores an `Arc` pointer into the atomic pointer if the current value is the same as the `current` value.
+    /// Fetches the value and applies `f` to it, like [`fetch_update`](Atomic::fetch_update),
+    /// but skips the exchange if `f` returns the stored pointer itself.
     ///
-    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
-    /// comparison succeeds, which can result in more efficient code on some platforms. The
-    /// return value is a result indicating whether the new value was written and containing the
-    /// previous value.
+    /// If the value returned by `f` is the same pointer with the same tag as the fetched
+    /// one, storing it would not change anything, so `Ok(previous_value)` is returned
+    /// without a compare-and-exchange. This saves contention on idempotent updates. In
+    /// that case no store takes place, so `set_order` does not apply and the value was
+    /// only loaded with `fetch_order`.
     ///
-    /// `compare_exchange_weak` takes two [`Ordering`] arguments to describe the memory
-    /// ordering of this operation. `success` describes the required ordering for the
-    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
-    /// `failure` describes the required ordering for the load operation that takes place when
-    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
-    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
-    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
-    /// and must be equivalent to or weaker than the success ordering.
-    fn compare_exchange_weak(
+    /// # Panics
+    ///
+    /// Panics if `fetch_order` is `Release` or `AcqRel`.
+    #[must_use = "the result tells whether the value was updated"]
+    pub fn fetch_update_dedup<F>(
         &self,
-        current: impl Into<Arc<T>>,
-        new: impl Into<Arc<T>>,
-        success: Ordering,
-        failure: Ordering,
-    ) -> Result<Arc<T>, Arc<T>> {
-        let current: Arc<T> = current.into();
-        let current = Arc::into_raw(current) as usize;
-        let new: Arc<T> = new.into();
-        let new = Arc::into_raw(new) as usize;
-        unsafe {
-            transmute::<&NonNull<T>, &AtomicUsize>(&self.data)
-                .compare_exchange_weak(current, new, success, failure)
-                .map(|ok| {
-                    Arc::from_raw(ok as *const T)
-                })
-                .map_err(|err| {
-                    Arc::from_raw(err as *const T)
-                })
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<<Self as Atomic>::Elem, <Self as Atomic>::Elem>
+    where
+        F: FnMut(&<Self as Atomic>::Elem) -> Option<<Self as Atomic>::Elem>,
+    {
+        let backoff = Backoff::new();
+        let mut prev = self.load(fetch_order);
+        while let Some(next) = f(&prev) {
+            if Self::data_of(&next) == Self::data_of(&prev) {
+                return Ok(prev)
+            }
+            match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
+                x @ Ok(_) => return x,
+                Err(next_prev) => prev = next_prev
+            }
+            backoff.snooze();
         }
+        Err(prev)
     }
 
-    // /// Fetches the value, and applies a function to it that returns an optional
-    // /// new value. Returns a `Result` of `Ok(previous_value)` if the function
-    // /// returned `Some(_)`, else `Err(previous_value)`.
-    // ///
-    // /// Note: This may call the function multiple times if the value has been
-    // /// changed from other threads in the meantime, as long as the function
-    // /// returns `Some(_)`, but the function will have been applied only once to
-    // /// the stored value.
-    // ///
-    // /// Note: This does not protect the program from the ABA problem. 
-    // ///
-    // /// `fetch_update` takes two [`Ordering`] arguments to describe the memory
-    // /// ordering of this operation. The first describes the required ordering for
-    // /// when the operation finally succeeds while the second describes the
-    // /// required ordering for loads. These correspond to the success and failure
-    // /// orderings of [`AtomicPtr::compare_exchange`] respectively.
-    // ///
-    // /// Using [`Acquire`] as success ordering makes the store part of this
-    // /// operation [`Relaxed`], and using [`Release`] makes the final successful
-    // /// load [`Relaxed`]. The (failed) load ordering can only be [`SeqCst`],
-    // /// [`Acquire`] or [`Relaxed`] and must be equivalent to or weaker than the
-    // /// success ordering.
-    // fn fetch_update<F>(
-    //     &self,
-    //     set_order: Ordering,
-    //     fetch_order: Ordering,
-    //     mut f: F
-    // ) -> Result<Arc<T>, Arc<T>>
-    // where 
-    //     F: FnMut(&Arc<T>) -> Option<Arc<T>>
-    // {
-    //     let mut prev = self.load(fetch_order);
-    //     while let Some(next) = f(&prev) {
-    //         match self.compare_exchange_weak(prev, next, set_order, fetch_order) {
-    //             x @ Ok(_) => return x,
-    //             Err(next_prev) => prev = next_prev,
-    //         }
-    //     }
-    //     Err(prev)
-    // }
-}
-
-impl<T> From<Arc<T>> for AtomicArc<T> {
-    fn from(val: Arc<T>) -> Self {
-        Self::from_arc(val)
-    }
-}
-
-impl<T> From<T> for AtomicArc<T> {
-    fn from(val: T) -> Self {
-        Self::new(val)
+    /// Loads the stored pointer without the tag and without cloning the `Arc`.
+    ///
+    /// This is meant for identity checks, such as whether the head of a list has changed.
+    /// The returned pointer must not be dereferenced, because a concurrent store may
+    /// release the value at any time. For the same reason, the address of a released
+    /// value may be reused, so an unchanged pointer does not prove that no store happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[cfg(feature = "tag")]
+    pub fn load_ptr(&self, order: Ordering) -> *const T {
+        assert_load_ordering(order);
+        (self.data.load(order) & !low_bits::<T>()) as *const T
     }
-}
 
-impl<T> Clone for AtomicArc<T> {
-    fn clone(&self) -> Self {
-        unimplemented!()
+    /// Loads the stored pointer without the tag and without cloning the `Arc`.
+    ///
+    /// This is meant for identity checks, such as whether the head of a list has changed.
+    /// The returned pointer must not be dereferenced, because a concurrent store may
+    /// release the value at any time. For the same reason, the address of a released
+    /// value may be reused, so an unchanged pointer does not prove that no store happened.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[cfg(not(feature = "tag"))]
+    pub fn load_ptr(&self, order: Ordering) -> *const T {
+        assert_load_ordering(order);
+        self.data.load(order) as *const T
     }
-}
 
-#[cfg(test)]
+    /// Loads the tag of the stored pointer.
+    ///
+    /// Always returns 0 if `feature = "tag"` is disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[cfg(feature = "tag")]
+    pub fn tag(&self, order: Ordering) -> usize {
+        assert_load_ordering(order);
+        self.data.load(order) & low_bits::<T>()
+    }
+
+    /// Loads the tag of the stored pointer.
+    ///
+    /// Always returns 0 if `feature = "tag"` is disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[cfg(not(feature = "tag"))]
+    pub fn tag(&self, order: Ordering) -> usize {
+        assert_load_ordering(order);
+        0
+    }
+
+    /// Replaces the tag of the stored pointer, leaving the pointer untouched.
+    ///
+    /// The bits of `tag` that do not fit into the pointer are discarded. This does
+    /// nothing if `feature = "tag"` is disabled.
+    ///
+    /// `set_tag` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation. All ordering modes are possible.
+    #[cfg(feature = "tag")]
+    pub fn set_tag(&self, tag: usize, order: Ordering) {
+        let _ = self.data.fetch_update(order, failure_ordering(order), |data| {
+            Some(compose_tag::<T>(data, tag))
+        });
+    }
+
+    /// Replaces the tag of the stored pointer, leaving the pointer untouched.
+    ///
+    /// The bits of `tag` that do not fit into the pointer are discarded. This does
+    /// nothing if `feature = "tag"` is disabled.
+    ///
+    /// `set_tag` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation. All ordering modes are possible.
+    #[cfg(not(feature = "tag"))]
+    pub fn set_tag(&self, _tag: usize, _order: Ordering) {}
+
+    /// Loads the stored value as a plain `Arc`, discarding the tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[cfg(feature = "tag")]
+    pub fn load_arc(&self, order: Ordering) -> Arc<T> {
+        assert_load_ordering(order);
+        self.reclaim.protect(|| {
+            let data = self.data.load(order) & !low_bits::<T>();
+            // the stored reference still belongs to `self`
+            let ptr = ManuallyDrop::new(unsafe { Arc::from_raw(data as *const T) });
+            Arc::clone(&ptr)
+        })
+    }
+
+    /// Loads the stored value as a plain `Arc`, discarding the tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[cfg(not(feature = "tag"))]
+    pub fn load_arc(&self, order: Ordering) -> Arc<T> {
+        self.load(order)
+    }
+
+    /// Stores a plain `Arc` into the pointer, which clears the tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    #[cfg(feature = "tag")]
+    pub fn store_arc(&self, val: Arc<T>, order: Ordering) {
+        self.store(TaggedArc::from_arc(val), order)
+    }
+
+    /// Stores a plain `Arc` into the pointer, which clears the tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    #[cfg(not(feature = "tag"))]
+    pub fn store_arc(&self, val: Arc<T>, order: Ordering) {
+        self.store(val, order)
+    }
+
+    /// Returns the number of failed compare-and-swaps that the current thread performed
+    /// on any `AtomicArc`.
+    ///
+    /// A failure is genuine if another value was stored, and spurious if
+    /// `compare_exchange_weak` failed even though the stored value was `current`. A high
+    /// share of genuine failures in a retry loop points to contention rather than to the
+    /// hardware. Only available with `feature = "metrics"`.
+    #[cfg(feature = "metrics")]
+    pub fn cas_stats() -> super::CasStats {
+        super::metrics::cas_stats()
+    }
+
+    /// Calls `f` with a reference to the stored value and returns its result.
+    ///
+    /// The value is borrowed through a new reference that is taken as in
+    /// [`load`](Atomic::load) and held until `f` returns, so a concurrent `store` or
+    /// `swap` cannot free the value while `f` reads it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    pub fn with<R>(&self, order: Ordering, f: impl FnOnce(&T) -> R) -> R {
+        let ptr = self.load_arc(order);
+        f(&ptr)
+    }
+
+    /// Returns a guard through which loaded values are borrowed.
+    ///
+    /// The guard holds a reference to the value it loaded last, so the value stays alive
+    /// until the next load through the guard or until the guard is dropped. This makes the
+    /// scope of a loaded value explicit, in the same way as a `crossbeam_epoch::Guard`.
+    pub fn pin(&self) -> Guard<'_, T> {
+        Guard {
+            cell: self,
+            current: None,
+        }
+    }
+
+    /// Returns a view of the value derived from the stored one by `f`.
+    ///
+    /// Each [`load`](Projected::load) through the view loads the stored value and projects
+    /// it again, so the view follows later stores. Use [`with`](AtomicArc::with) to project
+    /// a single snapshot instead.
+    pub fn project<U, F>(&self, f: F) -> Projected<'_, T, F>
+    where
+        F: Fn(&T) -> U,
+    {
+        Projected {
+            cell: self,
+            f,
+        }
+    }
+
+    /// Stores `new` if the stored pointer is `current`, without consuming `current`.
+    ///
+    /// `current` has no tag, so the comparison only succeeds if the stored tag is zero;
+    /// see [`compare_exchange_ptr`](AtomicArc::compare_exchange_ptr) to ignore the tag.
+    /// Otherwise this behaves like [`Atomic::compare_exchange`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    #[cfg(feature = "tag")]
+    pub fn compare_exchange_ref(
+        &self,
+        current: &Arc<T>,
+        new: Arc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        assert_failure_ordering(failure);
+        let expected = Arc::as_ptr(current) as usize;
+        let new = Arc::into_raw(new) as usize;
+
+        self.run_cas(expected, |data| data.compare_exchange(expected, new, success, failure))
+            .map_err(|(_, actual)| {
+                // `new` is not stored and must be released
+                drop(unsafe { Arc::from_raw(new as *const T) });
+                actual
+            })
+    }
+
+    /// Stores `new` if the stored pointer is `current`, without consuming `current`.
+    ///
+    /// Otherwise this behaves like [`Atomic::compare_exchange`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    #[cfg(not(feature = "tag"))]
+    pub fn compare_exchange_ref(
+        &self,
+        current: &Arc<T>,
+        new: Arc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<T>, Arc<T>> {
+        assert_failure_ordering(failure);
+        let expected = Arc::as_ptr(current) as usize;
+        let new = Arc::into_raw(new) as usize;
+
+        self.run_cas(expected, |data| data.compare_exchange(expected, new, success, failure))
+            .map_err(|(_, actual)| {
+                // `new` is not stored and must be released
+                drop(unsafe { Arc::from_raw(new as *const T) });
+                actual
+            })
+    }
+
+    /// Stores `val` into the pointer, returning the previous value.
+    ///
+    /// This is [`swap`](Atomic::swap) with `AcqRel` ordering.
+    pub fn replace(&self, val: impl Into<<Self as Atomic>::Elem>) -> <Self as Atomic>::Elem {
+        self.swap(val, Ordering::AcqRel)
+    }
+
+    /// Exchanges the values stored in `self` and `other`, including their tags.
+    ///
+    /// This is not a single atomic operation on the pair. The value of `other` is loaded
+    /// and swapped into `self`, and the previous value of `self` is then stored into
+    /// `other` if `other` still holds the loaded value. Other threads can observe both
+    /// cells holding the same value in between. Each step is atomic on its own cell,
+    /// with `order` as in [`swap`](Atomic::swap).
+    ///
+    /// If another thread changed `other` in between, `other` keeps that value and the
+    /// previous value of `self` is returned as the error, while `self` already holds
+    /// the loaded value.
+    pub fn exchange(&self, other: &AtomicArc<T>, order: Ordering) -> Result<(), <Self as Atomic>::Elem> {
+        let fetch_order = failure_ordering(order);
+        let theirs = other.load(fetch_order);
+        let ours = self.swap(theirs.clone(), order);
+        // `ours` is kept until the exchange succeeds, so that it can be handed back
+        match other.compare_exchange(theirs, ours.clone(), order, fetch_order) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ours),
+        }
+    }
+
+    /// Stores the value returned by `f` into the pointer, returning the previous value.
+    ///
+    /// This is [`swap`](Atomic::swap) with a value that is built by `f`, which is
+    /// called exactly once.
+    pub fn swap_from_fn<F>(&self, order: Ordering, f: F) -> <Self as Atomic>::Elem
+    where
+        F: FnOnce() -> Arc<T>,
+    {
+        self.swap(f(), order)
+    }
+
+    /// Stores the value returned by `f` into the pointer if the current value is the
+    /// same as `current`.
+    ///
+    /// `f` is only called once the stored value has been found equal to `current`, so
+    /// no new value is built if the comparison fails. The value may still be changed
+    /// by another thread before it is exchanged, in which case the value returned by
+    /// `f` is dropped. Otherwise this behaves like [`Atomic::compare_exchange`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_from_fn<F>(
+        &self,
+        current: impl Into<<Self as Atomic>::Elem>,
+        f: F,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<<Self as Atomic>::Elem, <Self as Atomic>::Elem>
+    where
+        F: FnOnce() -> Arc<T>,
+    {
+        assert_failure_ordering(failure);
+        let current = current.into();
+        let expected = Self::data_of(&current);
+        let stored = self.reclaim.protect(|| {
+            let stored = self.data.load(failure);
+            // SAFETY: `stored` was loaded from `self`
+            (stored != expected).then(|| unsafe { Self::clone_stored(stored) })
+        });
+        if let Some(stored) = stored {
+            return Err(stored)
+        }
+        self.compare_exchange(current, f(), success, failure)
+    }
+
+    /// Returns the value that `ptr` is stored as
+    #[cfg(feature = "tag")]
+    fn data_of(ptr: &TaggedArc<T>) -> usize {
+        ptr.data.as_ptr() as usize
+    }
+
+    /// Returns the value that `ptr` is stored as
+    #[cfg(not(feature = "tag"))]
+    fn data_of(ptr: &Arc<T>) -> usize {
+        Arc::as_ptr(ptr) as usize
+    }
+
+    /// Returns a new reference to the pointer `data`, which is still held by `self`
+    #[cfg(not(feature = "tag"))]
+    unsafe fn clone_stored(data: usize) -> Arc<T> {
+        let ptr = ManuallyDrop::new(Arc::from_raw(data as *const T));
+        Arc::clone(&ptr)
+    }
+
+    /// Releases the reference held by the pointer `data`
+    #[cfg(feature = "tag")]
+    unsafe fn release(data: usize) {
+        drop(TaggedArc::<T>::from_usize(data))
+    }
+
+    /// Releases the reference held by the pointer `data`
+    #[cfg(not(feature = "tag"))]
+    unsafe fn release(data: usize) {
+        drop(Arc::from_raw(data as *const T))
+    }
+
+    /// Takes over the reference of the pointer `data`, which was just replaced in `self`
+    ///
+    /// Another thread may still be loading the value, so a new reference is returned
+    /// in that case, and the one of `self` is retired.
+    unsafe fn take_replaced(&self, data: usize) -> <Self as Atomic>::Elem {
+        if self.reclaim.is_quiescent() {
+            #[cfg(feature = "tag")]
+            return TaggedArc::from_usize(data)
+                .expect("AtomicArc pointer must be non-zero");
+            #[cfg(not(feature = "tag"))]
+            return Arc::from_raw(data as *const T);
+        }
+        let ptr = Self::clone_stored(data);
+        self.reclaim.retire(data, Self::release);
+        ptr
+    }
+
+    /// Runs `cas`, a compare-and-swap on the stored pointer that expects `current`.
+    ///
+    /// On success, the reference of the replaced value is taken over. On failure, the
+    /// stored pointer is returned with a new reference to it, which is taken before a
+    /// concurrent store can release the value.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn run_cas(
+        &self,
+        current: usize,
+        cas: impl FnOnce(&AtomicUsize) -> Result<usize, usize>,
+    ) -> Result<<Self as Atomic>::Elem, (usize, <Self as Atomic>::Elem)> {
+        let result = self.reclaim.protect(|| {
+            let result = cas(&self.data);
+            #[cfg(feature = "test-hooks")]
+            super::hooks::notify_cas(result.is_ok());
+            #[cfg(feature = "metrics")]
+            super::metrics::record_cas(current, &result);
+            // SAFETY: the pointer was found in `self`
+            result.map_err(|err| (err, unsafe { Self::clone_stored(err) }))
+        });
+        result.map(|ok| unsafe { self.take_replaced(ok) })
+    }
+
+    /// Wraps `new` so that it carries the same tag as `like`
+    #[cfg(feature = "tag")]
+    fn with_tag_of(new: Arc<T>, like: &TaggedArc<T>) -> TaggedArc<T> {
+        TaggedArc::from_arc_with_tag(new, like.tag())
+    }
+
+    /// Wraps `new` so that it carries the same tag as `like`
+    #[cfg(not(feature = "tag"))]
+    fn with_tag_of(new: Arc<T>, _like: &Arc<T>) -> Arc<T> {
+        new
+    }
+}
+
+/// A scope for values loaded from an `AtomicArc`, returned by [`AtomicArc::pin`]
+///
+/// The guard holds a reference to the last loaded value, which is released when the
+/// guard loads again or is dropped.
+pub struct Guard<'a, T> {
+    cell: &'a AtomicArc<T>,
+    current: Option<Arc<T>>,
+}
+
+impl<'a, T> Guard<'a, T> {
+    /// Loads the stored value and borrows it for as long as the guard is not used again.
+    ///
+    /// The reference to the previously loaded value is released. The reference is taken
+    /// as in [`AtomicArc::load_arc`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    pub fn load(&mut self, order: Ordering) -> &T {
+        self.current.insert(self.cell.load_arc(order))
+    }
+}
+
+/// A view of a value derived from the one stored in an `AtomicArc`, returned by
+/// [`AtomicArc::project`]
+pub struct Projected<'a, T, F> {
+    cell: &'a AtomicArc<T>,
+    f: F,
+}
+
+impl<'a, T, U, F> Projected<'a, T, F>
+where
+    F: Fn(&T) -> U,
+{
+    /// Loads the stored value and returns its projection.
+    ///
+    /// The value is borrowed as in [`AtomicArc::with`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    pub fn load(&self, order: Ordering) -> U {
+        self.cell.with(order, &self.f)
+    }
+}
+
+/// A mutable handle to the pointer stored in an `AtomicArc`, returned by
+/// [`AtomicArc::get_mut`] when `feature = "tag"` is disabled.
+///
+/// The handle holds the reference of the `AtomicArc`, and its `Arc` is stored back
+/// when the handle is dropped.
+#[cfg(not(feature = "tag"))]
+pub struct ArcMut<'a, T> {
+    ptr: ManuallyDrop<Arc<T>>,
+    data: &'a mut AtomicUsize,
+}
+
+#[cfg(not(feature = "tag"))]
+impl<'a, T> core::ops::Deref for ArcMut<'a, T> {
+    type Target = Arc<T>;
+
+    fn deref(&self) -> &Arc<T> {
+        &self.ptr
+    }
+}
+
+#[cfg(not(feature = "tag"))]
+impl<'a, T> core::ops::DerefMut for ArcMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut Arc<T> {
+        &mut self.ptr
+    }
+}
+
+#[cfg(not(feature = "tag"))]
+impl<'a, T> Drop for ArcMut<'a, T> {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` is not used after it is taken
+        let ptr = unsafe { ManuallyDrop::take(&mut self.ptr) };
+        // hands the reference back to the `AtomicArc`, whose previous reference was
+        // taken over by `ptr` or released when `ptr` was replaced
+        self.data.store(Arc::into_raw(ptr) as usize, Ordering::Relaxed);
+    }
+}
+
+impl<T: Clone> AtomicArc<T> {
+    /// Returns a mutable reference to the stored value, cloning it first if it is shared
+    ///
+    /// This is like [`Arc::make_mut`](alloc::sync::Arc::make_mut) for the stored pointer. The
+    /// tag is kept when the value is cloned into a new allocation. Unlike `Arc::make_mut`,
+    /// a value that is only shared with `Weak` pointers is cloned as well.
+    pub fn make_mut(&mut self) -> &mut T {
+        // `&mut self` guarantees that no other thread is accessing the pointer
+        let tag = self.tag(Ordering::Relaxed);
+        // the reference is still owned by `self`
+        let mut arc = ManuallyDrop::new(unsafe { Arc::from_raw(self.load_ptr(Ordering::Relaxed)) });
+        if Arc::get_mut(&mut arc).is_none() {
+            // releases the reference of `self`
+            *arc = Arc::new(T::clone(&arc));
+        }
+        let raw = Arc::into_raw(ManuallyDrop::into_inner(arc));
+        self.data.store(raw as usize | tag, Ordering::Relaxed);
+        // SAFETY: `self` holds the only reference to the value
+        unsafe { &mut *(raw as *mut T) }
+    }
+
+    /// Applies a fallible transform to the stored value and returns the updated `Arc`.
+    ///
+    /// `f` is applied to a private copy of the current value, so other threads
+    /// never observe a partially updated value. The copy is made on every call,
+    /// also when the cell holds the only reference: through `&self`, another
+    /// thread may load the value at any time, so it is never safe to write it in
+    /// place. [`make_mut`](Self::make_mut) updates a unique value without a copy
+    /// through `&mut self`.
+    ///
+    /// The copy is installed with `compare_exchange_weak`; if the value was
+    /// changed by another thread in the meantime, the process is repeated on the
+    /// new value, so `f` may be called more than once.
+    ///
+    /// If `f` returns `Err(_)`, the stored value is left unchanged and the error
+    /// is returned. The tag is carried over to the new value if `feature = "tag"`
+    /// is enabled.
+    ///
+    /// `try_map_in_place` takes an `Ordering` argument which describes the memory
+    /// ordering of the store when it succeeds. The loads use the strongest failure
+    /// ordering allowed for `order`.
+    pub fn try_map_in_place<F, E>(&self, mut f: F, order: Ordering) -> Result<Arc<T>, E>
+    where
+        F: FnMut(&mut T) -> Result<(), E>
+    {
+        let fetch_order = failure_ordering(order);
+        let backoff = Backoff::new();
+        let mut current = self.load(fetch_order);
+        loop {
+            #[allow(clippy::useless_conversion)]
+            let prev: Arc<T> = current.clone().into();
+            let mut value = T::clone(&prev);
+            f(&mut value)?;
+            let next = Arc::new(value);
+
+            let new = Self::with_tag_of(next.clone(), &current);
+            match self.compare_exchange_weak(current, new, order, fetch_order) {
+                Ok(_) => return Ok(next),
+                Err(actual) => current = actual,
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+#[cfg(feature = "tag")]
+impl<T> AtomicArc<T> {
+    /// Returns a new reference to the pointer `data`, which is still held by `self`
+    unsafe fn clone_stored(data: usize) -> TaggedArc<T> {
+        let ptr = ManuallyDrop::new(
+            TaggedArc::from_usize(data)
+                .expect("AtomicArc pointer must be non-zero")
+        );
+        TaggedArc::clone(&ptr)
+    }
+
+    /// Bitwise "and" with the current tag.
+    ///
+    /// Performs a bitwise "and" operation on the current tag and the argument `val`, and
+    /// sets the new tag to the result. Only the unused lower bits of the pointer are
+    /// affected; the pointer itself is left untouched.
+    ///
+    /// Returns the previous value.
+    ///
+    /// `fetch_and` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation. All ordering modes are possible. Note that using `Acquire` makes
+    /// the store part of this operation `Relaxed`, and using `Release` makes the load
+    /// part `Relaxed`.
+    pub fn fetch_and(&self, val: usize, order: Ordering) -> TaggedArc<T> {
+        // keep all the pointer bits set so that they are not cleared
+        let val = val | !low_bits::<T>();
+        self.reclaim.protect(|| unsafe {
+            let prev = self.data
+                .fetch_and(val, order);
+            Self::clone_stored(prev)
+        })
+    }
+
+    /// Bitwise "or" with the current tag.
+    ///
+    /// Performs a bitwise "or" operation on the current tag and the argument `val`, and
+    /// sets the new tag to the result. Only the unused lower bits of the pointer are
+    /// affected; the pointer itself is left untouched.
+    ///
+    /// Returns the previous value.
+    ///
+    /// `fetch_or` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation. All ordering modes are possible. Note that using `Acquire` makes
+    /// the store part of this operation `Relaxed`, and using `Release` makes the load
+    /// part `Relaxed`.
+    pub fn fetch_or(&self, val: usize, order: Ordering) -> TaggedArc<T> {
+        let val = val & low_bits::<T>();
+        self.reclaim.protect(|| unsafe {
+            let prev = self.data
+                .fetch_or(val, order);
+            Self::clone_stored(prev)
+        })
+    }
+
+    /// Bitwise "xor" with the current tag.
+    ///
+    /// Performs a bitwise "xor" operation on the current tag and the argument `val`, and
+    /// sets the new tag to the result. Only the unused lower bits of the pointer are
+    /// affected; the pointer itself is left untouched.
+    ///
+    /// Returns the previous value.
+    ///
+    /// `fetch_xor` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation. All ordering modes are possible. Note that using `Acquire` makes
+    /// the store part of this operation `Relaxed`, and using `Release` makes the load
+    /// part `Relaxed`.
+    pub fn fetch_xor(&self, val: usize, order: Ordering) -> TaggedArc<T> {
+        let val = val & low_bits::<T>();
+        self.reclaim.protect(|| unsafe {
+            let prev = self.data
+                .fetch_xor(val, order);
+            Self::clone_stored(prev)
+        })
+    }
+
+    /// Returns the mask of tag bit `idx`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than [`TaggedArc::TAG_BITS`].
+    fn tag_bit(idx: u32) -> usize {
+        assert!(
+            idx < TaggedArc::<T>::TAG_BITS,
+            "tag bit {} does not fit into the unused bits of the pointer", idx
+        );
+        1 << idx
+    }
+
+    /// Sets tag bit `idx` and returns whether it was set before.
+    ///
+    /// `bit_set` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation, like [`fetch_or`](AtomicArc::fetch_or).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than [`TaggedArc::TAG_BITS`].
+    pub fn bit_set(&self, idx: u32, order: Ordering) -> bool {
+        let bit = Self::tag_bit(idx);
+        self.data.fetch_or(bit, order) & bit != 0
+    }
+
+    /// Clears tag bit `idx` and returns whether it was set before.
+    ///
+    /// `bit_clear` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation, like [`fetch_and`](AtomicArc::fetch_and).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than [`TaggedArc::TAG_BITS`].
+    pub fn bit_clear(&self, idx: u32, order: Ordering) -> bool {
+        let bit = Self::tag_bit(idx);
+        self.data.fetch_and(!bit, order) & bit != 0
+    }
+
+    /// Flips tag bit `idx` and returns whether it was set before.
+    ///
+    /// `bit_toggle` takes an `Ordering` argument which describes the memory ordering of
+    /// this operation, like [`fetch_xor`](AtomicArc::fetch_xor).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than [`TaggedArc::TAG_BITS`].
+    pub fn bit_toggle(&self, idx: u32, order: Ordering) -> bool {
+        let bit = Self::tag_bit(idx);
+        self.data.fetch_xor(bit, order) & bit != 0
+    }
+
+    /// Stores `new` into the atomic pointer if the stored pointer is the same as `current`,
+    /// whatever the stored tag is.
+    ///
+    /// The return value is a result indicating whether the new value was written and
+    /// containing the previous value, including its tag. `new` is dropped if it is not
+    /// written.
+    ///
+    /// This is not a hardware primitive. It is emulated with a loop of
+    /// `compare_exchange` that retries whenever only the tag has changed in the meantime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_ptr(
+        &self,
+        current: &Arc<T>,
+        new: impl Into<TaggedArc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        assert_failure_ordering(failure);
+        let expected = Arc::as_ptr(current) as usize;
+        let new: TaggedArc<T> = new.into();
+        let new = new.into_usize();
+
+        let result = self.reclaim.protect(|| {
+            let backoff = Backoff::new();
+            let mut prev = self.data.load(failure);
+            loop {
+                if prev & !low_bits::<T>() != expected {
+                    // SAFETY: `prev` was loaded from `self`
+                    return Err(unsafe { Self::clone_stored(prev) })
+                }
+                let result = self.data.compare_exchange(prev, new, success, failure);
+                #[cfg(feature = "test-hooks")]
+                super::hooks::notify_cas(result.is_ok());
+                #[cfg(feature = "metrics")]
+                super::metrics::record_cas(prev, &result);
+
+                match result {
+                    Ok(ok) => return Ok(ok),
+                    Err(actual) => prev = actual,
+                }
+                backoff.snooze();
+            }
+        });
+
+        match result {
+            // takes over the reference that was held by `self`
+            Ok(ok) => Ok(unsafe { self.take_replaced(ok) }),
+            Err(actual) => {
+                // `new` is not stored and must be released
+                drop(unsafe { TaggedArc::<T>::from_usize(new) });
+                Err(actual)
+            }
+        }
+    }
+
+    /// Stores `new` into the atomic pointer if the stored tagged address is `current`.
+    ///
+    /// `current` is a raw token, such as the address returned by [`TaggedArc::into_usize`],
+    /// so no reference has to be held for the expected value. On success the replaced value
+    /// is released, and on failure `new` is handed back.
+    ///
+    /// If the allocation behind a cached token has been freed, its address may be reused by
+    /// a new value, which then compares equal to the token. Algorithms that cache tokens
+    /// must rule this out, for example by keeping a reference alive or changing the tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_usize(
+        &self,
+        current: usize,
+        new: TaggedArc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(), TaggedArc<T>> {
+        assert_failure_ordering(failure);
+        let new = new.into_usize();
+        let result = self.data.compare_exchange(current, new, success, failure);
+        #[cfg(feature = "test-hooks")]
+        super::hooks::notify_cas(result.is_ok());
+        #[cfg(feature = "metrics")]
+        super::metrics::record_cas(current, &result);
+
+        match result {
+            // releases the reference that was held by `self`
+            Ok(prev) => unsafe { self.reclaim.retire(prev, Self::release) },
+            // `new` is not stored and is handed back
+            Err(_) => return Err(unsafe {
+                TaggedArc::from_usize(new)
+                    .expect("AtomicArc pointer must be non-zero")
+            }),
+        }
+        Ok(())
+    }
+
+    /// Stores `new` into the atomic pointer if the current value is the same as `current`,
+    /// including the tag, and reports why the exchange failed otherwise.
+    ///
+    /// This behaves like [`Atomic::compare_exchange`], but the error tells whether the
+    /// stored pointer still matched `current` and only the tag differed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_detailed(
+        &self,
+        current: &TaggedArc<T>,
+        new: impl Into<TaggedArc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, CasFailure<T>> {
+        assert_failure_ordering(failure);
+        let expected = current.data.as_ptr() as usize;
+        let new: TaggedArc<T> = new.into();
+        let new = new.into_usize();
+
+        self.run_cas(expected, |data| data.compare_exchange(expected, new, success, failure))
+            .map_err(|(err, current)| {
+                // `new` is not stored and must be released
+                drop(unsafe { TaggedArc::<T>::from_usize(new) });
+                CasFailure {
+                    current,
+                    ptr_matched: err & !low_bits::<T>() == expected & !low_bits::<T>(),
+                }
+            })
+    }
+
+    /// Stores `new` if the stored value is `current`, including the tag, without
+    /// consuming `current`.
+    ///
+    /// `current` can be reused after a failure, so a retry loop does not need to clone
+    /// the expected value on every attempt. Otherwise this behaves like
+    /// [`Atomic::compare_exchange`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_tagged(
+        &self,
+        current: &TaggedArc<T>,
+        new: impl Into<TaggedArc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        self.compare_exchange_by_ref(current, new.into(), success, failure, false)
+    }
+
+    /// Stores `new` if the stored value is `current`, including the tag, without
+    /// consuming `current`.
+    ///
+    /// Unlike [`compare_exchange_tagged`](AtomicArc::compare_exchange_tagged), this
+    /// function is allowed to spuriously fail even when the comparison succeeds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_weak_tagged(
+        &self,
+        current: &TaggedArc<T>,
+        new: impl Into<TaggedArc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        self.compare_exchange_by_ref(current, new.into(), success, failure, true)
+    }
+
+    fn compare_exchange_by_ref(
+        &self,
+        current: &TaggedArc<T>,
+        new: TaggedArc<T>,
+        success: Ordering,
+        failure: Ordering,
+        weak: bool,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        assert_failure_ordering(failure);
+        let expected = current.data.as_ptr() as usize;
+        let new = new.into_usize();
+
+        let result = self.run_cas(expected, |data| if weak {
+            data.compare_exchange_weak(expected, new, success, failure)
+        } else {
+            data.compare_exchange(expected, new, success, failure)
+        });
+
+        result.map_err(|(_, actual)| {
+            // `new` is not stored and must be released
+            drop(unsafe { TaggedArc::<T>::from_usize(new) });
+            actual
+        })
+    }
+}
+
+/// The error of [`AtomicArc::compare_exchange_detailed`]
+#[cfg(feature = "tag")]
+#[derive(Debug)]
+pub struct CasFailure<T> {
+    /// The value stored when the exchange failed
+    pub current: TaggedArc<T>,
+    /// `true` if the stored pointer matched and only the tag differed
+    pub ptr_matched: bool,
+}
+
+#[cfg(feature = "tag")]
+impl<T> Atomic for AtomicArc<T> {
+    type Elem = TaggedArc<T>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes 
+    /// the memory ordering of this operation. 
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    /// 
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> TaggedArc<T> {
+        assert_load_ordering(order);
+        // no value replaced in the meantime is released before it is cloned
+        self.reclaim.protect(|| {
+            let ptr = unsafe {
+                let addr = self.data
+                    .load(order);
+                // the stored reference still belongs to `self`
+                ManuallyDrop::new(
+                    TaggedArc::from_usize(addr)
+                        .expect("AtomicArc pointer must be non-zero")
+                )
+            };
+            // clone because `load` does not give away ownership
+            TaggedArc::clone(&ptr)
+        })
+    }
+
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes 
+    /// the memory ordering of this operation. 
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    /// 
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<TaggedArc<T>>, order: Ordering) {
+        assert_store_ordering(order);
+        let ptr: TaggedArc<T> = val.into();
+        let new_data = ptr.into_usize();
+        // the previous value must be released once it is replaced
+        unsafe {
+            let old_data = self.data
+                .swap(new_data, order);
+            self.reclaim.retire(old_data, Self::release)
+        }
+    }
+
+    /// Stores a `TaggedArc` pointer into the atomic pointer, returning the previously stored pointer
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation. 
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this 
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.            
+    fn swap(&self, val: impl Into<TaggedArc<T>>, order: Ordering) -> TaggedArc<T> {
+        let ptr: TaggedArc<T> = val.into();
+        let new_data = ptr.into_usize();
+        // let old_data = self.data.swap(new_data, order);
+        
+        // SAFETY: only raw Arc pointers will be stored in the pointer
+        unsafe {
+            let old_data = self.data
+                .swap(new_data, order);
+            self.take_replaced(old_data)
+        }
+    }   
+
+    /// Stores a `TaggedArc` pointer into the if the current value is the same as the `current` value.
+    /// The tag will also be compared.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. On success this value is guaranteed to be equal to `current`.
+    ///
+    /// `compare_exchange` takes two [`Ordering`] arguments to describe the memory
+    /// ordering of this operation. `success` describes the required ordering for the
+    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
+    /// `failure` describes the required ordering for the load operation that takes place when
+    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
+    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
+    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
+    /// and must be equivalent to or weaker than the success ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<TaggedArc<T>>,
+        new: impl Into<TaggedArc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: TaggedArc<T> = current.into();
+        let new: TaggedArc<T> = new.into();
+        let new = new.into_usize();
+
+        let expected = current.data.as_ptr() as usize;
+        self.run_cas(expected, |data| data.compare_exchange(expected, new, success, failure))
+            .map_err(|(_, actual)| {
+                // `new` is not stored and must be released
+                drop(unsafe { TaggedArc::<T>::from_usize(new) });
+                actual
+            })
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer if the current value is the same as the `current` value.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds, which can result in more efficient code on some platforms. The
+    /// return value is a result indicating whether the new value was written and containing the
+    /// previous value.
+    ///
+    /// `compare_exchange_weak` takes two [`Ordering`] arguments to describe the memory
+    /// ordering of this operation. `success` describes the required ordering for the
+    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
+    /// `failure` describes the required ordering for the load operation that takes place when
+    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
+    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
+    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
+    /// and must be equivalent to or weaker than the success ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<TaggedArc<T>>,
+        new: impl Into<TaggedArc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedArc<T>, TaggedArc<T>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: TaggedArc<T> = current.into();
+        let new: TaggedArc<T> = new.into();
+        let new = new.into_usize();
+        // self.data.compare_exchange_weak(current, new, success, failure)
+        //     .map(|success| {
+        //         unsafe{ TaggedArc::from_usize(success) }
+        //     })
+        //     .map_err(|failure| {
+        //         unsafe{ TaggedArc::from_usize(failure) }
+        //     })
+
+        let expected = current.data.as_ptr() as usize;
+        self.run_cas(expected, |data| data.compare_exchange_weak(expected, new, success, failure))
+            .map_err(|(_, actual)| {
+                // `new` is not stored and must be released
+                drop(unsafe { TaggedArc::<T>::from_usize(new) });
+                actual
+            })
+    }
+
+    /// Stores a `TaggedArc` pointer into the atomic pointer if the current value is the same
+    /// as the `current` value, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous value and a new reference to `new`. On failure, which
+    /// may be spurious, returns the current value and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<TaggedArc<T>>,
+        new: TaggedArc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(TaggedArc<T>, TaggedArc<T>), (TaggedArc<T>, TaggedArc<T>)> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: TaggedArc<T> = current.into();
+        let new = new.into_usize();
+
+        let expected = current.data.as_ptr() as usize;
+        let mut installed = None;
+        let result = self.run_cas(expected, |data| {
+            let result = data.compare_exchange_weak(expected, new, success, failure);
+            if result.is_ok() {
+                // `new` is cloned before a concurrent store can release it
+                installed = Some(unsafe { Self::clone_stored(new) });
+            }
+            result
+        });
+        match result {
+            Ok(prev) => Ok((prev, installed.expect("a stored value must be cloned"))),
+            // `new` is not stored, so its reference is handed back
+            Err((_, actual)) => Err((actual, unsafe { TaggedArc::from_usize(new).expect("AtomicArc pointer must be non-zero") })),
+        }
+    }
+}
+
+#[cfg(not(feature = "tag"))]
+impl<T> Atomic for AtomicArc<T> {
+    type Elem = Arc<T>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes 
+    /// the memory ordering of this operation. 
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    /// 
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> Arc<T> {
+        assert_load_ordering(order);
+        // no value replaced in the meantime is released before it is cloned
+        self.reclaim.protect(|| {
+            let ptr = unsafe {
+                let addr = self.data
+                    .load(order);
+                // the stored reference still belongs to `self`
+                ManuallyDrop::new(Arc::from_raw(addr as *const T))
+            };
+            // clone because `load` doesn't give away ownership
+            Arc::clone(&ptr)
+        })
+    }
+
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes 
+    /// the memory ordering of this operation. 
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    /// 
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<Arc<T>>, order: Ordering) {
+        assert_store_ordering(order);
+        let ptr: Arc<T> = val.into();
+        let new_data = Arc::into_raw(ptr) as usize;
+        // the previous value must be released once it is replaced
+        unsafe {
+            let old_data = self.data
+                .swap(new_data, order);
+            self.reclaim.retire(old_data, Self::release)
+        }
+    }
+
+    /// Stores a `Arc` pointer into the atomic pointer, returning the previously stored pointer
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation. 
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this 
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<Arc<T>>, order: Ordering) -> Arc<T> {
+        let ptr: Arc<T> = val.into();
+        let new_data = Arc::into_raw(ptr) as usize;
+        // SAFETY: only raw Arc pointers will be stored in the pointer
+        unsafe {
+            let old_data = self.data
+                .swap(new_data, order);
+            self.take_replaced(old_data)
+        }
+    }
+
+    /// Stores a `Arc` pointer into the if the current value is the same as the `current` value.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. On success this value is guaranteed to be equal to `current`.
+    ///
+    /// `compare_exchange` takes two [`Ordering`] arguments to describe the memory
+    /// ordering of this operation. `success` describes the required ordering for the
+    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
+    /// `failure` describes the required ordering for the load operation that takes place when
+    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
+    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
+    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
+    /// and must be equivalent to or weaker than the success ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<Arc<T>>,
+        new: impl Into<Arc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<T>, Arc<T>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: Arc<T> = current.into();
+        let new: Arc<T> = new.into();
+        let new = Arc::into_raw(new) as usize;
+
+        let expected = Arc::as_ptr(&current) as usize;
+        self.run_cas(expected, |data| data.compare_exchange(expected, new, success, failure))
+            .map_err(|(_, actual)| {
+                // `new` is not stored and must be released
+                drop(unsafe { Arc::from_raw(new as *const T) });
+                actual
+            })
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer if the current value is the same as the `current` value.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds, which can result in more efficient code on some platforms. The
+    /// return value is a result indicating whether the new value was written and containing the
+    /// previous value.
+    ///
+    /// `compare_exchange_weak` takes two [`Ordering`] arguments to describe the memory
+    /// ordering of this operation. `success` describes the required ordering for the
+    /// read-modify-write operation that takes place if the comparison with `current` succeeds.
+    /// `failure` describes the required ordering for the load operation that takes place when
+    /// the comparison fails. Using [`Acquire`] as success ordering makes the store part
+    /// of this operation [`Relaxed`], and using [`Release`] makes the successful load
+    /// [`Relaxed`]. The failure ordering can only be [`SeqCst`], [`Acquire`] or [`Relaxed`]
+    /// and must be equivalent to or weaker than the success ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<Arc<T>>,
+        new: impl Into<Arc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<T>, Arc<T>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: Arc<T> = current.into();
+        let new: Arc<T> = new.into();
+        let new = Arc::into_raw(new) as usize;
+        let expected = Arc::as_ptr(&current) as usize;
+        self.run_cas(expected, |data| data.compare_exchange_weak(expected, new, success, failure))
+            .map_err(|(_, actual)| {
+                // `new` is not stored and must be released
+                drop(unsafe { Arc::from_raw(new as *const T) });
+                actual
+            })
+    }
+
+    /// Stores an `Arc` pointer into the atomic pointer if the current value is the same as
+    /// the `current` value, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous value and a new reference to `new`. On failure, which
+    /// may be spurious, returns the current value and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Arc<T>>,
+        new: Arc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Arc<T>, Arc<T>), (Arc<T>, Arc<T>)> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: Arc<T> = current.into();
+        let new = Arc::into_raw(new) as usize;
+
+        let expected = Arc::as_ptr(&current) as usize;
+        let mut installed = None;
+        let result = self.run_cas(expected, |data| {
+            let result = data.compare_exchange_weak(expected, new, success, failure);
+            if result.is_ok() {
+                // `new` is cloned before a concurrent store can release it
+                installed = Some(unsafe { Self::clone_stored(new) });
+            }
+            result
+        });
+        match result {
+            Ok(prev) => Ok((prev, installed.expect("a stored value must be cloned"))),
+            // `new` is not stored, so its reference is handed back
+            Err((_, actual)) => Err((actual, unsafe { Arc::from_raw(new as *const T) })),
+        }
+    }
+}
+
+impl<T> From<Arc<T>> for AtomicArc<T> {
+    fn from(val: Arc<T>) -> Self {
+        Self::from_arc(val)
+    }
+}
+
+/// Consumes the atomic and returns the stored value, discarding the tag
+impl<T> From<AtomicArc<T>> for Arc<T> {
+    fn from(val: AtomicArc<T>) -> Self {
+        val.into_arc()
+    }
+}
+
+#[cfg(feature = "tag")]
+impl<T> From<TaggedArc<T>> for AtomicArc<T> {
+    fn from(val: TaggedArc<T>) -> Self {
+        Self::from_tagged(val)
+    }
+}
+
+/// Moves the boxed value into a new `AtomicArc`.
+///
+/// The conversion goes through `Arc::from`, which reuses the allocation of the `Box`.
+#[cfg(not(feature = "loom"))]
+impl<T> From<Box<T>> for AtomicArc<T> {
+    fn from(val: Box<T>) -> Self {
+        Self::from_arc(Arc::from(val))
+    }
+}
+
+impl<T> From<T> for AtomicArc<T> {
+    fn from(val: T) -> Self {
+        Self::new(val)
+    }
+}
+
+impl<T> Clone for AtomicArc<T> {
+    /// Creates a new `AtomicArc` that holds its own reference to the currently stored
+    /// value, including its tag if `feature = "tag"` is enabled.
+    fn clone(&self) -> Self {
+        // the loaded pointer owns a new strong reference, which is handed to the clone
+        Self::from(self.load(Ordering::Acquire))
+    }
+}
+
+impl<T: Default> Default for AtomicArc<T> {
+    /// Creates an `AtomicArc` that holds `T::default()` with a zero tag
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// Formats a snapshot of the stored value, which may be outdated as soon as it is taken
+#[cfg(feature = "tag")]
+impl<T: fmt::Debug> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let current = self.load(Ordering::Acquire);
+        f.debug_struct("AtomicArc")
+            .field("value", &current)
+            .field("tag", &current.tag())
+            .finish()
+    }
+}
+
+/// Formats a snapshot of the stored value, which may be outdated as soon as it is taken
+#[cfg(not(feature = "tag"))]
+impl<T: fmt::Debug> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicArc")
+            .field("value", &self.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+/// Serializes a snapshot of the stored value. The tag is not serialized.
+#[cfg(all(feature = "serde", feature = "tag"))]
+impl<T: serde::Serialize> serde::Serialize for AtomicArc<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.load(Ordering::Acquire), serializer)
+    }
+}
+
+/// Serializes a snapshot of the stored value.
+#[cfg(all(feature = "serde", not(feature = "tag")))]
+impl<T: serde::Serialize> serde::Serialize for AtomicArc<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize(&*self.load(Ordering::Acquire), serializer)
+    }
+}
+
+/// Deserializes the value into a new `Arc` with a tag of 0.
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for AtomicArc<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(|val| Self::from_arc(Arc::new(val)))
+    }
+}
+
+impl<T> Drop for AtomicArc<T> {
+    fn drop(&mut self) {
+        // `&mut self` guarantees that no other thread is accessing the pointer
+        let data = self.data.load(Ordering::Relaxed);
+
+        // SAFETY: `self` owns one strong reference of the stored pointer
+        #[cfg(feature = "tag")]
+        drop(unsafe { TaggedArc::<T>::from_usize(data) });
+        #[cfg(not(feature = "tag"))]
+        drop(unsafe { Arc::from_raw(data as *const T) });
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+// the conversions to `Arc` are needed when `feature = "tag"` is enabled
+#[cfg_attr(not(feature = "tag"), allow(clippy::useless_conversion))]
 mod tests {
+    use core::{mem::transmute_copy, num::NonZeroUsize};
+
     use super::*;
 
+    #[derive(Debug, Clone, PartialEq)]
+    struct Config {
+        retries: u32,
+    }
+
+    #[test]
+    fn try_map_in_place_success() {
+        let cell = AtomicArc::new(Config { retries: 1 });
+        let out: Result<_, ()> = cell.try_map_in_place(|c| {
+            c.retries += 1;
+            Ok(())
+        }, Ordering::AcqRel);
+        assert_eq!(out.unwrap().retries, 2);
+
+        let current: Arc<Config> = cell.load(Ordering::Acquire).into();
+        assert_eq!(current.retries, 2);
+    }
+
+    #[test]
+    fn try_map_in_place_failure() {
+        let cell = AtomicArc::new(Config { retries: 1 });
+        let out = cell.try_map_in_place(|c| {
+            c.retries += 1;
+            Err("too many retries")
+        }, Ordering::AcqRel);
+        assert_eq!(out, Err("too many retries"));
+
+        let current: Arc<Config> = cell.load(Ordering::Acquire).into();
+        assert_eq!(current.retries, 1);
+    }
+
+    #[test]
+    fn try_map_in_place_shared() {
+        let cell = AtomicArc::new(Config { retries: 1 });
+        let before: Arc<Config> = cell.load(Ordering::Acquire).into();
+        let after = cell.try_map_in_place(|c| {
+            c.retries = 5;
+            Ok::<_, ()>(())
+        }, Ordering::AcqRel).unwrap();
+
+        // the outstanding reference is not affected by the update
+        assert_eq!(before.retries, 1);
+        assert_eq!(after.retries, 5);
+        assert!(!Arc::ptr_eq(&before, &after));
+    }
+
+    #[test]
+    fn try_map_in_place_copies_on_write() {
+        let cell = AtomicArc::new(Config { retries: 1 });
+        let shared: Arc<Config> = cell.load(Ordering::Acquire).into();
+        let updated = cell.try_map_in_place(|c| {
+            c.retries += 1;
+            Ok::<_, ()>(())
+        }, Ordering::AcqRel).unwrap();
+
+        // the shared value is copied rather than written, and the cell lets go of it
+        assert_eq!(shared.retries, 1);
+        assert_eq!(Arc::strong_count(&shared), 1);
+
+        let current: Arc<Config> = cell.load(Ordering::Acquire).into();
+        assert!(Arc::ptr_eq(&current, &updated));
+        assert_eq!(Arc::strong_count(&updated), 3);
+    }
+
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn drop_releases_stored_arc() {
+        let value = Arc::new(13);
+        let cell = AtomicArc::from_arc(value.clone());
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        let loaded = cell.load(Ordering::Acquire);
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(loaded);
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn drop_frees_last_value() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let cell = AtomicArc::new(DropCounter(dropped.clone()));
+
+        // the replaced value is freed by `store`
+        cell.store(Arc::new(DropCounter(dropped.clone())), Ordering::Release);
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+        drop(cell);
+        assert_eq!(dropped.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn compare_exchange_balances_counts() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_arc(a.clone());
+
+        let out = cell.compare_exchange(b.clone(), b.clone(), Ordering::AcqRel, Ordering::Acquire);
+        assert!(out.is_err());
+        drop(out);
+        assert_eq!(Arc::strong_count(&a), 2);
+        assert_eq!(Arc::strong_count(&b), 1);
+
+        let out = cell.compare_exchange(a.clone(), b.clone(), Ordering::AcqRel, Ordering::Acquire);
+        assert!(out.is_ok());
+        drop(out);
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 2);
+    }
+
+    #[test]
+    fn compare_and_set_releases_new_on_failure() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_arc(a.clone());
+
+        assert!(!cell.compare_and_set(b.clone(), b.clone(), Ordering::AcqRel));
+        assert_eq!(Arc::strong_count(&a), 2);
+        assert_eq!(Arc::strong_count(&b), 1);
+    }
+
+    #[test]
+    fn compare_and_set_releases_old_on_success() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_arc(a.clone());
+
+        assert!(cell.compare_and_set(a.clone(), b.clone(), Ordering::AcqRel));
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 2);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&b), 1);
+    }
+
+    #[test]
+    fn compare_exchange_drop_balances_counts() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let c = Arc::new(3);
+        let cell = AtomicArc::from_arc(a.clone());
+
+        assert!(!cell.compare_exchange_drop(b.clone(), c.clone(), Ordering::AcqRel, Ordering::Acquire));
+        assert_eq!(Arc::strong_count(&a), 2);
+        assert_eq!(Arc::strong_count(&b), 1);
+        assert_eq!(Arc::strong_count(&c), 1);
+
+        assert!(cell.compare_exchange_drop(a.clone(), b.clone(), Ordering::AcqRel, Ordering::Acquire));
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 2);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&b), 1);
+    }
+
+    #[test]
+    fn into_arc_through_from() {
+        let value = Arc::new(13);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        let inner: Arc<i32> = cell.into();
+        assert!(Arc::ptr_eq(&value, &inner));
+        assert_eq!(Arc::strong_count(&value), 2);
+    }
+
+    #[test]
+    fn into_inner_returns_same_allocation() {
+        let value = Arc::new(13);
+        let cell = AtomicArc::from_arc(value.clone());
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        let inner: Arc<i32> = cell.into_inner().into();
+        assert!(Arc::ptr_eq(&value, &inner));
+        assert_eq!(Arc::as_ptr(&value), Arc::as_ptr(&inner));
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        drop(inner);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn into_inner_keeps_tag() {
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(Arc::new(13), 0b11));
+        let inner = cell.into_inner();
+        assert_eq!(inner.tag(), 0b11);
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let value = Arc::new(21);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        let raw = cell.into_raw();
+        assert_eq!(raw, Arc::as_ptr(&value));
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        let cell = unsafe { AtomicArc::from_raw(raw) };
+        assert_eq!(*cell.load(Ordering::Acquire), 21);
+        drop(cell);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn raw_round_trip_keeps_tag() {
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(Arc::new(21), 0b11));
+
+        let raw = cell.into_raw();
+        let cell = unsafe { AtomicArc::from_raw(raw) };
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.tag(), 0b11);
+        assert_eq!(*current, 21);
+    }
+
+    #[test]
+    fn into_arc_keeps_count() {
+        let value = Arc::new(8);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        let out = cell.into_arc();
+        assert!(Arc::ptr_eq(&out, &value));
+        assert_eq!(Arc::strong_count(&value), 2);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn into_arc_strips_tag() {
+        let value = Arc::new(8);
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(value.clone(), 0b11));
+
+        let out = cell.into_arc();
+        assert!(Arc::ptr_eq(&out, &value));
+        assert_eq!(*out, 8);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn get_mut_replaces_value() {
+        let mut cell = AtomicArc::new(1);
+        *cell.get_mut() = TaggedArc::from_arc_with_tag(Arc::new(2), 0b1);
+
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.tag(), 0b1);
+        assert_eq!(*current.into_arc(), 2);
+    }
+
+    #[cfg(not(feature = "tag"))]
+    #[test]
+    fn get_mut_replaces_value() {
+        let old = Arc::new(1);
+        let mut cell = AtomicArc::from_arc(old.clone());
+        *cell.get_mut() = Arc::new(2);
+
+        assert_eq!(*cell.load(Ordering::Acquire), 2);
+        assert_eq!(Arc::strong_count(&old), 1);
+    }
+
+    #[cfg(not(feature = "tag"))]
+    #[test]
+    fn get_mut_is_unique_without_other_references() {
+        let mut cell = AtomicArc::new(1);
+        *Arc::get_mut(&mut *cell.get_mut()).unwrap() += 1;
+        assert_eq!(*cell.load(Ordering::Acquire), 2);
+
+        let other: Arc<i32> = cell.load(Ordering::Acquire);
+        assert!(Arc::get_mut(&mut *cell.get_mut()).is_none());
+        drop(other);
+        assert_eq!(Arc::strong_count(&*cell.get_mut()), 1);
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let cell: AtomicArc<String> = AtomicArc::new(String::from("old"));
+        let copy = cell.clone();
+
+        cell.store(Arc::new(String::from("new")), Ordering::Release);
+        let original: Arc<String> = cell.load(Ordering::Acquire).into();
+        let cloned: Arc<String> = copy.load(Ordering::Acquire).into();
+        assert_eq!(*original, "new");
+        assert_eq!(*cloned, "old");
+    }
+
+    #[test]
+    fn clone_takes_a_reference() {
+        let value = Arc::new(13);
+        let cell = AtomicArc::from_arc(value.clone());
+        let copy = cell.clone();
+        assert_eq!(Arc::strong_count(&value), 3);
+
+        drop(cell);
+        drop(copy);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn clone_keeps_tag() {
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(Arc::new(13), 0b10));
+        let copy = cell.clone();
+        assert_eq!(copy.load(Ordering::Acquire).tag(), 0b10);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn fetch_bit_ops_on_tag() {
+        let value = Arc::new(42u64);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        assert_eq!(cell.fetch_or(0b001, Ordering::AcqRel).tag(), 0b000);
+        assert_eq!(cell.fetch_xor(0b110, Ordering::AcqRel).tag(), 0b001);
+        assert_eq!(cell.fetch_and(0b101, Ordering::AcqRel).tag(), 0b111);
+        assert_eq!(cell.load(Ordering::Acquire).tag(), 0b101);
+
+        // bits beyond the tag are masked out
+        let prev = cell.fetch_xor(!0, Ordering::AcqRel);
+        assert_eq!(prev.as_raw(), Arc::as_ptr(&value));
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.tag(), 0b010);
+        assert_eq!(current.as_raw(), Arc::as_ptr(&value));
+
+        drop((prev, current, cell));
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn fetch_bit_ops_concurrent() {
+        let value = Arc::new(42u64);
+        let cell = Arc::new(AtomicArc::from_arc(value.clone()));
+
+        let handles: Vec<_> = (0..3)
+            .map(|i| {
+                let cell = cell.clone();
+                std::thread::spawn(move || {
+                    let bit = 1 << i;
+                    for _ in 0..1000 {
+                        let prev = cell.fetch_or(bit, Ordering::AcqRel);
+                        assert_eq!(prev.tag() & bit, 0);
+                        let prev = cell.fetch_and(!bit, Ordering::AcqRel);
+                        assert_eq!(prev.tag() & bit, bit);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.tag(), 0);
+        assert_eq!(current.as_raw(), Arc::as_ptr(&value));
+        assert_eq!(*current.into_arc(), 42);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn update_counts_concurrently() {
+        let cell = Arc::new(AtomicArc::new(0u64));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cell = cell.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        cell.update(Ordering::AcqRel, |prev| {
+                            TaggedArc::new(*prev.clone().into_arc() + 1)
+                        });
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(*cell.load(Ordering::Acquire).into_arc(), 4000);
+    }
+
+    #[test]
+    fn debug_shows_value() {
+        let cell: AtomicArc<String> = AtomicArc::new(String::from("hello"));
+        assert!(format!("{:?}", cell).contains("\"hello\""));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let cell = AtomicArc::from_arc(Arc::new(String::from("config")));
+        let json = serde_json::to_string(&cell).unwrap();
+        assert_eq!(json, "\"config\"");
+
+        let cell: AtomicArc<String> = serde_json::from_str(&json).unwrap();
+        let current = cell.load(Ordering::Acquire);
+        #[cfg(feature = "tag")]
+        assert_eq!(current.tag(), 0);
+        let current: Arc<String> = current.into();
+        assert_eq!(*current, "config");
+    }
+
+    #[test]
+    fn fetch_update_replaces_from_current() {
+        let cell: AtomicArc<String> = AtomicArc::new(String::from("hello"));
+        let prev = cell.fetch_update(Ordering::AcqRel, Ordering::Acquire, |prev| {
+            let prev: Arc<String> = prev.clone().into();
+            Some(Arc::new(format!("{}, world", prev)).into())
+        });
+        let prev: Arc<String> = prev.unwrap().into();
+        assert_eq!(*prev, "hello");
+
+        let current: Arc<String> = cell.load(Ordering::Acquire).into();
+        assert_eq!(*current, "hello, world");
+
+        // returning `None` leaves the value unchanged
+        assert!(cell.fetch_update(Ordering::AcqRel, Ordering::Acquire, |_| None).is_err());
+        assert_eq!(Arc::strong_count(&current), 2);
+    }
+
+    #[test]
+    fn fetch_update_new_returns_installed_value() {
+        let cell: AtomicArc<u32> = AtomicArc::new(1);
+        let mut produced = None;
+        let new = cell
+            .fetch_update_new(Ordering::AcqRel, Ordering::Acquire, |prev| {
+                let next: Arc<u32> = Arc::new(**prev + 1);
+                produced = Some(next.clone());
+                Some(next.into())
+            })
+            .unwrap();
+        let new: Arc<u32> = new.into();
+        assert!(Arc::ptr_eq(&new, produced.as_ref().unwrap()));
+        assert_eq!(*cell.load(Ordering::Acquire), 2);
+
+        let unchanged = cell
+            .fetch_update_new(Ordering::AcqRel, Ordering::Acquire, |_| None)
+            .unwrap_err();
+        assert_eq!(*unchanged, 2);
+    }
+
+    #[test]
+    fn compare_exchange_weak_keep_hands_back_new() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_arc(a.clone());
+
+        let (actual, new) = cell
+            .compare_exchange_weak_keep(b.clone(), b.clone().into(), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        let (actual, mut new): (Arc<i32>, Arc<i32>) = (actual.into(), new.into());
+        assert!(Arc::ptr_eq(&actual, &a));
+        assert!(Arc::ptr_eq(&new, &b));
+        drop(actual);
+        assert_eq!(Arc::strong_count(&a), 2);
+        assert_eq!(Arc::strong_count(&b), 2);
+
+        // the weak exchange may fail spuriously, and is retried with the returned value
+        let (prev, installed) = loop {
+            match cell.compare_exchange_weak_keep(a.clone(), new.into(), Ordering::AcqRel, Ordering::Acquire) {
+                Ok(ok) => break ok,
+                Err((_, returned)) => new = returned.into(),
+            }
+        };
+        let (prev, installed): (Arc<i32>, Arc<i32>) = (prev.into(), installed.into());
+        assert!(Arc::ptr_eq(&prev, &a));
+        assert!(Arc::ptr_eq(&installed, &b));
+        drop((prev, installed));
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 2);
+    }
+
+    #[test]
+    fn new_cyclic_refers_to_itself() {
+        struct Node {
+            this: std::sync::Weak<Node>,
+        }
+
+        let cell = AtomicArc::new_cyclic(|this| Node { this: this.clone() });
+        let current = cell.load(Ordering::Acquire);
+        #[cfg(feature = "tag")]
+        assert_eq!(current.tag(), 0);
+        let current: Arc<Node> = current.into();
+        let this = current.this.upgrade().unwrap();
+        assert!(Arc::ptr_eq(&this, &current));
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn compare_exchange_ptr_ignores_tag() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(a.clone(), 0b11));
+
+        let prev = cell
+            .compare_exchange_ptr(&a, TaggedArc::from_arc_with_tag(b.clone(), 0b01), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert_eq!(prev.tag(), 0b11);
+        assert_eq!(prev.as_raw(), Arc::as_ptr(&a));
+        drop(prev);
+        assert_eq!(Arc::strong_count(&a), 1);
+
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.tag(), 0b01);
+        assert_eq!(current.as_raw(), Arc::as_ptr(&b));
+        drop(current);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn compare_exchange_usize_with_loaded_token() {
+        let a = Arc::new(1);
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(a.clone(), 0b10));
+
+        let token = cell.load(Ordering::Acquire).into_usize();
+        let stale = TaggedArc::from_arc(Arc::new(2)).into_usize();
+        let rejected = cell
+            .compare_exchange_usize(stale, TaggedArc::from_arc(Arc::new(3)), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert_eq!(*rejected, 3);
+        assert_eq!(Arc::strong_count(&a), 3);
+
+        cell.compare_exchange_usize(token, TaggedArc::from_arc(Arc::new(4)), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert_eq!(*cell.load(Ordering::Acquire), 4);
+        assert_eq!(Arc::strong_count(&a), 2);
+
+        // releases the references handed over to the tokens
+        drop(unsafe { (TaggedArc::<i32>::from_usize(token), TaggedArc::<i32>::from_usize(stale)) });
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn compare_exchange_ptr_fails_on_other_pointer() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(b.clone(), 0b10));
+
+        let actual = cell
+            .compare_exchange_ptr(&a, a.clone(), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert_eq!(actual.tag(), 0b10);
+        assert_eq!(actual.as_raw(), Arc::as_ptr(&b));
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 3);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn compare_exchange_detailed_reports_tag_change() {
+        let a = Arc::new(1);
+        let cell = AtomicArc::from_tagged(TaggedArc::from_arc_with_tag(a.clone(), 0b01));
+        let loaded = cell.load(Ordering::Acquire);
+
+        // only the tag changed
+        cell.set_tag(0b10, Ordering::AcqRel);
+        let tag_failure = cell
+            .compare_exchange_detailed(&loaded, Arc::new(2), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert!(tag_failure.ptr_matched);
+        assert_eq!(tag_failure.current.tag(), 0b10);
+
+        // the value was replaced
+        cell.store(Arc::new(3), Ordering::Release);
+        let failure = cell
+            .compare_exchange_detailed(&tag_failure.current, Arc::new(2), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert!(!failure.ptr_matched);
+        assert_eq!(*failure.current, 3);
+
+        let prev = cell
+            .compare_exchange_detailed(&failure.current, Arc::new(4), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert_eq!(*prev, 3);
+        drop((loaded, tag_failure, failure, prev));
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn compare_exchange_tagged_keeps_current() {
+        let value = Arc::new(1);
+        let cell = AtomicArc::new(value.clone());
+        let current = cell.load(Ordering::Acquire);
+
+        // the tag changes, so `current` no longer matches
+        cell.set_tag(0b1, Ordering::Release);
+        let actual = cell
+            .compare_exchange_tagged(&current, Arc::new(2), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert_eq!(actual.tag(), 0b1);
+        assert_eq!(*current, 1);
+
+        // `current` is reused for the retry
+        cell.set_tag(0, Ordering::Release);
+        let mut new = TaggedArc::new(2).with_tag(0b10);
+        loop {
+            match cell.compare_exchange_weak_tagged(&current, new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(prev) => {
+                    assert_eq!(prev, current);
+                    break
+                },
+                Err(_) => new = TaggedArc::new(2).with_tag(0b10),
+            }
+        }
+        // `value`, `current` and the failed exchange's `actual` remain
+        assert_eq!((*current, Arc::strong_count(&value)), (1, 3));
+        drop(actual);
+        assert_eq!(Arc::strong_count(&value), 2);
+        assert_eq!(cell.tag(Ordering::Acquire), 0b10);
+        assert_eq!(*cell.load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn exchange_crosses_values_over() {
+        let (a, b) = (Arc::new(1), Arc::new(2));
+        let first = AtomicArc::new(a.clone());
+        let second = AtomicArc::new(b.clone());
+        second.set_tag(0b1, Ordering::Relaxed);
+        // the tag is always zero without `feature = "tag"`
+        let tag = if cfg!(feature = "tag") { 0b1 } else { 0 };
+
+        assert!(first.exchange(&second, Ordering::AcqRel).is_ok());
+        assert_eq!((*first.load(Ordering::Acquire), first.tag(Ordering::Acquire)), (2, tag));
+        assert_eq!((*second.load(Ordering::Acquire), second.tag(Ordering::Acquire)), (1, 0));
+        assert_eq!((Arc::strong_count(&a), Arc::strong_count(&b)), (2, 2));
+
+        assert!(second.exchange(&first, Ordering::AcqRel).is_ok());
+        assert_eq!(*first.load(Ordering::Acquire), 1);
+        assert_eq!(*second.load(Ordering::Acquire), 2);
+        assert_eq!((Arc::strong_count(&a), Arc::strong_count(&b)), (2, 2));
+    }
+
+    #[test]
+    fn exchange_reports_concurrent_stores() {
+        const ROUNDS: i32 = 100;
+
+        let first = AtomicArc::new(0);
+        let second = AtomicArc::new(-1);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..=ROUNDS {
+                    second.store(Arc::new(i), Ordering::Release);
+                }
+            });
+
+            for _ in 0..ROUNDS {
+                let before: Arc<i32> = first.load(Ordering::Acquire).into();
+                if let Err(ours) = first.exchange(&second, Ordering::AcqRel) {
+                    // the value of `first` is handed back rather than lost
+                    assert!(Arc::ptr_eq(&ours.into(), &before));
+                }
+            }
+        });
+
+        // every value ends up in one of the cells or is released
+        let values = [*first.load(Ordering::Acquire), *second.load(Ordering::Acquire)];
+        assert!(values.iter().all(|&value| (-1..=ROUNDS).contains(&value)));
+    }
+
+    #[test]
+    fn default_holds_default_value() {
+        let cell = AtomicArc::<Vec<i32>>::default();
+        assert!(cell.load(Ordering::Relaxed).is_empty());
+        assert_eq!(cell.tag(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn load_consume_matches_acquire_load() {
+        let cell = AtomicArc::new(1);
+        let consumed = cell.load_consume();
+        let acquired = cell.load(Ordering::Acquire);
+        assert_eq!(consumed, acquired);
+        let consumed: Arc<i32> = consumed.into();
+        assert_eq!(*consumed, 1);
+    }
+
+    #[test]
+    fn compare_exchange_ref_keeps_current() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::new(a.clone());
+
+        // failure
+        let actual = cell
+            .compare_exchange_ref(&b, Arc::new(3), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert_eq!(Arc::strong_count(&b), 1);
+        assert_eq!(*actual, 1);
+        drop(actual);
+        assert_eq!(Arc::strong_count(&a), 2);
+
+        // success
+        let prev = cell
+            .compare_exchange_ref(&a, b.clone(), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        drop(prev);
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::strong_count(&b), 2);
+        assert_eq!(*cell.load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn rcu_appends_from_all_threads() {
+        let cell: Arc<AtomicArc<Vec<u32>>> = Arc::new(AtomicArc::new(Vec::new()));
+
+        let handles: Vec<_> = (0..4u32)
+            .map(|i| {
+                let cell = cell.clone();
+                std::thread::spawn(move || {
+                    for j in 0..100 {
+                        cell.rcu(|prev| {
+                            let mut next = prev.clone();
+                            next.push(i * 100 + j);
+                            next
+                        });
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let current: Arc<Vec<u32>> = cell.load(Ordering::Acquire).into();
+        let mut values = current.to_vec();
+        values.sort_unstable();
+        assert_eq!(values, (0..400).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn with_reads_field_during_stores() {
+        struct Counter {
+            count: usize,
+        }
+
+        let cell = Arc::new(AtomicArc::new(Counter { count: 0 }));
+        let writer = {
+            let cell = cell.clone();
+            std::thread::spawn(move || {
+                for count in 1..=1000 {
+                    cell.store_arc(Arc::new(Counter { count }), Ordering::Release);
+                }
+            })
+        };
+
+        let mut last = 0;
+        while last < 1000 {
+            let count = cell.with(Ordering::Acquire, |counter| counter.count);
+            assert!(count >= last);
+            last = count;
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn concurrent_loads_and_stores() {
+        const STORES: i32 = 100;
+
+        let cell = AtomicArc::new(0);
+        let weaks = std::thread::scope(|s| {
+            let writer = s.spawn(|| {
+                (1..=STORES)
+                    .map(|i| {
+                        // only the cell holds the stored value, so it is released as soon
+                        // as it is replaced and no load is in progress
+                        let value = Arc::new(i);
+                        let weak = Arc::downgrade(&value);
+                        if i % 2 == 0 {
+                            cell.store(value, Ordering::Release);
+                        } else {
+                            drop(cell.swap(value, Ordering::AcqRel));
+                        }
+                        weak
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            let mut last = 0;
+            while last < STORES {
+                let current: Arc<i32> = cell.load(Ordering::Acquire).into();
+                assert!(*current >= last);
+                last = *current;
+
+                // a failed exchange clones the loaded value as well
+                let stale = cell.compare_exchange(Arc::new(-1), Arc::new(-1), Ordering::AcqRel, Ordering::Acquire);
+                assert!(stale.is_err());
+            }
+            writer.join().unwrap()
+        });
+
+        drop(cell);
+        assert!(weaks.iter().all(|weak| weak.upgrade().is_none()));
+    }
+
+    #[test]
+    fn project_follows_stores() {
+        struct Settings {
+            name: String,
+            retries: u32,
+        }
+
+        let cell = AtomicArc::new(Settings { name: "first".into(), retries: 1 });
+        let name = cell.project(|settings| settings.name.clone());
+        assert_eq!(name.load(Ordering::Acquire), "first");
+
+        cell.store_arc(Arc::new(Settings { name: "second".into(), retries: 2 }), Ordering::Release);
+        assert_eq!(name.load(Ordering::Acquire), "second");
+        assert_eq!(cell.with(Ordering::Acquire, |settings| settings.retries), 2);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn bit_operations_return_previous_state() {
+        let value = Arc::new(3u64);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        assert!(!cell.bit_set(2, Ordering::AcqRel));
+        assert!(cell.bit_set(2, Ordering::AcqRel));
+        assert_eq!(cell.tag(Ordering::Acquire), 0b100);
+
+        assert!(!cell.bit_toggle(0, Ordering::AcqRel));
+        assert!(cell.bit_toggle(0, Ordering::AcqRel));
+        assert!(!cell.bit_toggle(0, Ordering::AcqRel));
+        assert_eq!(cell.tag(Ordering::Acquire), 0b101);
+
+        assert!(cell.bit_clear(2, Ordering::AcqRel));
+        assert!(!cell.bit_clear(2, Ordering::AcqRel));
+        assert_eq!(cell.tag(Ordering::Acquire), 0b001);
+
+        // the pointer and the count are untouched
+        assert!(Arc::ptr_eq(&cell.load_arc(Ordering::Acquire), &value));
+        assert_eq!(Arc::strong_count(&value), 2);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn bit_set_panics_outside_tag_bits() {
+        let cell = AtomicArc::new(1u32);
+        cell.bit_set(2, Ordering::AcqRel);
+    }
+
+    #[test]
+    fn get_mut_inner_only_when_unique() {
+        let mut cell = AtomicArc::<Vec<i32>>::new(vec![1]);
+        cell.get_mut_inner().unwrap().push(2);
+        assert_eq!(*cell.load_arc(Ordering::Acquire), [1, 2]);
+
+        let shared = cell.load_arc(Ordering::Acquire);
+        assert!(cell.get_mut_inner().is_none());
+        drop(shared);
+
+        let weak = Arc::downgrade(&cell.load_arc(Ordering::Acquire));
+        assert!(cell.get_mut_inner().is_none());
+        drop(weak);
+        assert!(cell.get_mut_inner().is_some());
+    }
+
+    #[test]
+    fn make_mut_leaves_shared_value_unchanged() {
+        let shared = Arc::new(vec![1]);
+        let mut cell = AtomicArc::<Vec<i32>>::from_arc(shared.clone());
+        cell.set_tag(0b1, Ordering::Relaxed);
+
+        cell.make_mut().push(2);
+        assert_eq!(*shared, [1]);
+        assert_eq!(Arc::strong_count(&shared), 1);
+        assert_eq!(*cell.load_arc(Ordering::Acquire), [1, 2]);
+        #[cfg(feature = "tag")]
+        assert_eq!(cell.tag(Ordering::Acquire), 0b1);
+    }
+
+    #[test]
+    fn swap_if_checks_contents() {
+        let first = Arc::new(1);
+        let cell = AtomicArc::from_arc(first.clone());
+        cell.set_tag(0b1, Ordering::Relaxed);
+
+        let rejected = cell.swap_if(Arc::new(2), |val| *val % 2 == 0, Ordering::AcqRel).unwrap_err();
+        assert!(Arc::ptr_eq(&rejected, &first));
+        assert_eq!(*cell.load_arc(Ordering::Acquire), 1);
+
+        let prev = cell.swap_if(Arc::new(2), |val| *val % 2 == 1, Ordering::AcqRel).unwrap();
+        assert!(Arc::ptr_eq(&prev, &first));
+        assert_eq!(*cell.load_arc(Ordering::Acquire), 2);
+        #[cfg(feature = "tag")]
+        assert_eq!(cell.tag(Ordering::Acquire), 0b1);
+
+        drop((rejected, prev));
+        assert_eq!(Arc::strong_count(&first), 1);
+    }
+
+    #[test]
+    fn zero_sized_values() {
+        let first = Arc::new(());
+        let cell = AtomicArc::<()>::from_arc(first.clone());
+        let second = Arc::new(());
+
+        let prev = cell.swap(second.clone(), Ordering::AcqRel);
+        assert!(Arc::ptr_eq(&prev.into(), &first));
+        assert!(Arc::ptr_eq(&cell.load_arc(Ordering::Acquire), &second));
+        assert_eq!(Arc::strong_count(&first), 1);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&second), 1);
+    }
+
+    #[test]
+    fn guard_holds_loaded_value() {
+        let first = Arc::new(String::from("first"));
+        let cell = AtomicArc::<String>::from_arc(first.clone());
+
+        let mut guard = cell.pin();
+        let value = guard.load(Ordering::Acquire);
+        cell.store_arc(Arc::new(String::from("second")), Ordering::Release);
+        assert_eq!(value, "first");
+        assert_eq!(Arc::strong_count(&first), 2);
+
+        assert_eq!(guard.load(Ordering::Acquire), "second");
+        assert_eq!(Arc::strong_count(&first), 1);
+
+        let second = cell.load_arc(Ordering::Acquire);
+        assert_eq!(Arc::strong_count(&second), 3);
+        drop(guard);
+        assert_eq!(Arc::strong_count(&second), 2);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn cas_stats_counts_genuine_failures() {
+        let cell = AtomicArc::new(1);
+        let before = AtomicArc::<i32>::cas_stats();
+
+        // another write replaces the value between the load and the exchange
+        let stale = cell.load(Ordering::Acquire);
+        cell.store_arc(Arc::new(2), Ordering::Release);
+        assert!(cell.compare_exchange(stale.clone(), Arc::new(3), Ordering::AcqRel, Ordering::Acquire).is_err());
+        assert!(cell.compare_exchange_weak(stale, Arc::new(3), Ordering::AcqRel, Ordering::Acquire).is_err());
+
+        let after = AtomicArc::<i32>::cas_stats();
+        assert_eq!(after.genuine_failures, before.genuine_failures + 2);
+
+        // a weak exchange with the stored value only fails spuriously
+        let current = cell.load(Ordering::Acquire);
+        while let Err(actual) = cell.compare_exchange_weak(current.clone(), Arc::new(4), Ordering::AcqRel, Ordering::Acquire) {
+            assert_eq!(actual, current);
+        }
+        assert_eq!(AtomicArc::<i32>::cas_stats().genuine_failures, after.genuine_failures);
+    }
+
+    #[test]
+    fn load_ptr_detects_store() {
+        let first = Arc::new(1);
+        let cell = Arc::new(AtomicArc::from_arc(first.clone()));
+        let seen = cell.load_ptr(Ordering::Acquire);
+        assert_eq!(seen, Arc::as_ptr(&first));
+        assert_eq!(Arc::strong_count(&first), 2);
+
+        let writer = {
+            let cell = cell.clone();
+            std::thread::spawn(move || cell.store_arc(Arc::new(2), Ordering::Release))
+        };
+        // `first` is kept alive, so its address cannot be reused
+        while cell.load_ptr(Ordering::Acquire) == seen {
+            std::hint::spin_loop();
+        }
+        writer.join().unwrap();
+        assert_eq!(Arc::strong_count(&first), 1);
+        assert_eq!(*cell.load_arc(Ordering::Acquire), 2);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn fetch_add_on_tag_bits() {
+        let value = Arc::new(5u64);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        // `u64` leaves three tag bits, so two increments cannot carry into the pointer
+        let storage = unsafe { cell.as_atomic_usize() };
+        storage.fetch_add(1, Ordering::AcqRel);
+        storage.fetch_add(1, Ordering::AcqRel);
+        assert_eq!(cell.tag(Ordering::Acquire), 2);
+
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.as_raw(), Arc::as_ptr(&value));
+        assert_eq!(*current, 5);
+    }
+
+    #[test]
+    fn replace_returns_previous() {
+        let cell = AtomicArc::<String>::new(String::from("old"));
+        let prev: Arc<String> = cell.replace(Arc::new(String::from("new"))).into();
+        assert_eq!(*prev, "old");
+        assert_eq!(*cell.load(Ordering::Acquire), "new");
+    }
+
+    #[test]
+    fn from_fn_calls_only_when_needed() {
+        use core::cell::Cell;
+
+        let calls = Cell::new(0);
+        let make = |val: i32| {
+            calls.set(calls.get() + 1);
+            Arc::new(val)
+        };
+        let cell = AtomicArc::new(1);
+
+        let prev: Arc<i32> = cell.swap_from_fn(Ordering::AcqRel, || make(2)).into();
+        assert_eq!(*prev, 1);
+        assert_eq!(calls.get(), 1);
+
+        // the comparison fails before `f` is called
+        let actual = cell
+            .compare_exchange_from_fn(prev, || make(3), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert_eq!(calls.get(), 1);
+
+        cell.compare_exchange_from_fn(actual, || make(3), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert_eq!(calls.get(), 2);
+        assert_eq!(*cell.load(Ordering::Acquire), 3);
+    }
+
+    #[cfg(feature = "tag")]
+    #[test]
+    fn set_tag_keeps_pointer() {
+        let value = Arc::new(3u64);
+        let cell = AtomicArc::from_arc(value.clone());
+        assert_eq!(cell.tag(Ordering::Acquire), 0);
+
+        cell.set_tag(0b101, Ordering::AcqRel);
+        assert_eq!(cell.tag(Ordering::Acquire), 0b101);
+        let current = cell.load(Ordering::Acquire);
+        assert_eq!(current.tag(), 0b101);
+        assert_eq!(current.as_raw(), Arc::as_ptr(&value));
+
+        // the bits that do not fit are discarded
+        cell.set_tag(0b1010, Ordering::AcqRel);
+        assert_eq!(cell.tag(Ordering::Acquire), 0b010);
+    }
+
+    #[cfg(not(feature = "tag"))]
+    #[test]
+    fn set_tag_is_a_no_op() {
+        let value = Arc::new(3u64);
+        let cell = AtomicArc::from_arc(value.clone());
+
+        cell.set_tag(0b101, Ordering::AcqRel);
+        assert_eq!(cell.tag(Ordering::Acquire), 0);
+        assert!(Arc::ptr_eq(&cell.load(Ordering::Acquire), &value));
+    }
+
+    #[test]
+    fn store_arc_clears_tag() {
+        let a = Arc::new(1);
+        let b = Arc::new(2);
+        let cell = AtomicArc::from_arc(a.clone());
+        cell.set_tag(0b11, Ordering::AcqRel);
+
+        let loaded = cell.load_arc(Ordering::Acquire);
+        assert!(Arc::ptr_eq(&loaded, &a));
+        assert_eq!(Arc::strong_count(&a), 3);
+        drop(loaded);
+
+        cell.store_arc(b.clone(), Ordering::Release);
+        assert_eq!(cell.tag(Ordering::Acquire), 0);
+        assert!(Arc::ptr_eq(&cell.load_arc(Ordering::Acquire), &b));
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no such thing as a release load")]
+    fn load_panics_on_release() {
+        let cell = AtomicArc::new(0);
+        let _ = cell.load(Ordering::Release);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no such thing as an acquire-release load")]
+    fn load_panics_on_acq_rel() {
+        let cell = AtomicArc::new(0);
+        let _ = cell.load(Ordering::AcqRel);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no such thing as an acquire store")]
+    fn store_panics_on_acquire() {
+        let cell = AtomicArc::new(0);
+        cell.store(Arc::new(1), Ordering::Acquire);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no such thing as an acquire-release store")]
+    fn store_panics_on_acq_rel() {
+        let cell = AtomicArc::new(0);
+        cell.store(Arc::new(1), Ordering::AcqRel);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no such thing as a release failure ordering")]
+    fn compare_exchange_panics_on_release_failure() {
+        let cell = AtomicArc::new(0);
+        let current = cell.load(Ordering::Relaxed);
+        let _ = cell.compare_exchange(current, Arc::new(1), Ordering::SeqCst, Ordering::Release);
+    }
+
+    #[test]
+    #[should_panic(expected = "there is no such thing as an acquire-release failure ordering")]
+    fn compare_exchange_weak_panics_on_acq_rel_failure() {
+        let cell = AtomicArc::new(0);
+        let current = cell.load(Ordering::Relaxed);
+        let _ = cell.compare_exchange_weak(current, Arc::new(1), Ordering::SeqCst, Ordering::AcqRel);
+    }
+
     #[test]
     fn test_transmute_nonzerousize_to_atomicusize() {
         let nz = NonZeroUsize::new(13).unwrap();
@@ -455,4 +2908,11 @@ mod tests {
         }
         println!("[3] nz becomes: {:?}", nz);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn from_box() {
+        let cell: AtomicArc<[u8; 1024]> = AtomicArc::from(Box::new([7u8; 1024]));
+        let arc: Arc<[u8; 1024]> = cell.load(Ordering::Acquire).into();
+        assert!(arc.iter().all(|b| *b == 7));
+    }
+}
@@ -0,0 +1,160 @@
+//! Deferred release of the values that a cell replaces while they are being loaded.
+//!
+//! A load reads the stored address and then increments the reference count behind it.
+//! A value that another thread replaces and releases in between would be freed before
+//! its count is incremented. [`Reclaim`] counts the threads that are between these two
+//! steps, and a value replaced while any of them is active is kept in a list until a
+//! later check finds none.
+
+use core::{ptr, sync::atomic::Ordering};
+
+use alloc::boxed::Box;
+
+use crate::primitives::{AtomicPtr, AtomicUsize};
+
+/// A replaced value that waits to be released
+struct Retired {
+    data: usize,
+    release: unsafe fn(usize),
+    next: *mut Retired,
+}
+
+/// The readers of a cell and the values that were replaced while they were active
+pub(crate) struct Reclaim {
+    // the number of threads that may be taking a reference to a value they loaded
+    readers: AtomicUsize,
+    // a stack of the values that wait for `readers` to drop to zero
+    retired: AtomicPtr<Retired>,
+}
+
+impl Reclaim {
+    #[cfg(not(feature = "loom"))]
+    pub(crate) const fn new() -> Self {
+        Self {
+            readers: AtomicUsize::new(0),
+            retired: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    #[cfg(feature = "loom")]
+    pub(crate) fn new() -> Self {
+        Self {
+            readers: AtomicUsize::new(0),
+            retired: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Runs `f`, which may load the address stored in the cell and take a new reference
+    /// to it. No value that is retired through `self` is released while `f` runs.
+    pub(crate) fn protect<R>(&self, f: impl FnOnce() -> R) -> R {
+        // pairs with the release of `is_quiescent`, so a value replaced before the check
+        // is not loaded by `f`
+        self.readers.fetch_add(1, Ordering::Acquire);
+        let _reader = Reader(self);
+        f()
+    }
+
+    /// Returns `true` if no thread may still take a reference to a value that was
+    /// replaced in the cell before the call.
+    pub(crate) fn is_quiescent(&self) -> bool {
+        // a read-modify-write rather than a load, so that a reader which comes later
+        // synchronizes with it and loads the replacing value
+        self.readers.fetch_add(0, Ordering::AcqRel) == 0
+    }
+
+    /// Calls `release` with `data` once no thread may still take a reference to it.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been replaced in the cell, which hands over the reference that
+    /// it held. `release` must release that reference.
+    pub(crate) unsafe fn retire(&self, data: usize, release: unsafe fn(usize)) {
+        if self.is_quiescent() {
+            return release(data)
+        }
+        let node = Box::into_raw(Box::new(Retired {
+            data,
+            release,
+            next: ptr::null_mut(),
+        }));
+        self.push(node, node);
+        // the readers may all have left before the push, without collecting the value
+        self.collect();
+    }
+
+    /// Pushes the chain from `first` to `last` onto the retired values
+    fn push(&self, first: *mut Retired, last: *mut Retired) {
+        let mut head = self.retired.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: the chain is owned by the caller until the exchange succeeds
+            unsafe { (*last).next = head };
+            match self.retired.compare_exchange_weak(head, first, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    /// Releases the retired values if no thread is loading, or puts them back otherwise
+    fn collect(&self) {
+        loop {
+            let first = self.retired.swap(ptr::null_mut(), Ordering::Acquire);
+            if first.is_null() {
+                return
+            }
+            if self.is_quiescent() {
+                // SAFETY: every value was replaced before the check, so no thread loads it
+                return unsafe { release_all(first) }
+            }
+            let mut last = first;
+            // SAFETY: the chain was taken off the list, so only this thread owns it
+            unsafe {
+                while !(*last).next.is_null() {
+                    last = (*last).next;
+                }
+            }
+            self.push(first, last);
+            // the last reader may have left before the push without seeing the values,
+            // otherwise it synchronizes with this check and collects them
+            if !self.is_quiescent() {
+                return
+            }
+        }
+    }
+}
+
+impl Drop for Reclaim {
+    fn drop(&mut self) {
+        // `&mut self` guarantees that no thread is loading from the cell
+        let first = self.retired.swap(ptr::null_mut(), Ordering::Acquire);
+        unsafe { release_all(first) }
+    }
+}
+
+/// Leaves the section entered by [`Reclaim::protect`], also if `f` panics
+struct Reader<'a>(&'a Reclaim);
+
+impl Drop for Reader<'_> {
+    fn drop(&mut self) {
+        let reclaim = self.0;
+        // the last reader releases the values that waited for it
+        if reclaim.readers.fetch_sub(1, Ordering::AcqRel) == 1
+            && !reclaim.retired.load(Ordering::Relaxed).is_null()
+        {
+            reclaim.collect();
+        }
+    }
+}
+
+/// Releases every value of the chain starting at `node`
+///
+/// # Safety
+///
+/// The chain must be owned by the caller, and no thread may take a reference to its values.
+unsafe fn release_all(mut node: *mut Retired) {
+    while !node.is_null() {
+        let retired = Box::from_raw(node);
+        node = retired.next;
+        (retired.release)(retired.data);
+    }
+}
@@ -0,0 +1,344 @@
+use core::{marker::PhantomData, mem::ManuallyDrop, ptr, sync::atomic::Ordering};
+
+use alloc::boxed::Box;
+
+use crate::primitives::Arc;
+
+use super::{Atomic, assert_failure_ordering, assert_load_ordering, assert_store_ordering};
+use super::reclaim::Reclaim;
+use super::stamped::{AtomicDWord, DWord, pack, unpack};
+
+/// Packs the data pointer and the length of `val`, handing over its reference
+fn into_data<T>(val: Arc<[T]>) -> DWord {
+    let len = val.len();
+    pack(Arc::into_raw(val) as *const T as usize, len)
+}
+
+/// Rebuilds the `Arc` packed by `into_data`
+///
+/// # Safety
+///
+/// `data` must hold a reference that is handed over to the returned `Arc`.
+unsafe fn from_data<T>(data: DWord) -> Arc<[T]> {
+    let (ptr, len) = unpack(data);
+    Arc::from_raw(ptr::slice_from_raw_parts(ptr as *const T, len))
+}
+
+/// An atomic pointer to a shared slice
+///
+/// `Arc<[T]>` is a fat pointer made of the data pointer and the length, so it
+/// does not fit into the single word that `AtomicArc` stores. `AtomicArcSlice`
+/// stores both words in a double-word atomic from `portable-atomic`, in the same
+/// way as [`AtomicArcStamped`](super::AtomicArcStamped), so a slice is stored
+/// without a further allocation. Tags are not supported.
+///
+/// `compare_exchange` compares the slices by address. As with
+/// [`AtomicArc`](super::AtomicArc), a slice that is replaced while another thread
+/// is loading it is released once no thread is loading from the cell any more.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::Ordering;
+/// use nolock::sync::{Atomic, AtomicArcSlice};
+///
+/// let cell = AtomicArcSlice::new(vec![1, 2, 3]);
+/// let prev = cell.swap(Arc::from(vec![4, 5]), Ordering::AcqRel);
+/// assert_eq!(*prev, [1, 2, 3]);
+/// assert_eq!(*cell.load(Ordering::Acquire), [4, 5]);
+/// ```
+pub struct AtomicArcSlice<T> {
+    // the data pointer in the lower word and the length in the upper word
+    data: AtomicDWord,
+    // the slices replaced while they were being loaded
+    reclaim: Reclaim,
+    _marker: PhantomData<Arc<[T]>>,
+}
+
+unsafe impl<T: Sync + Send> Send for AtomicArcSlice<T> {}
+unsafe impl<T: Sync + Send> Sync for AtomicArcSlice<T> {}
+
+impl<T> AtomicArcSlice<T> {
+    pub fn new(val: impl Into<Arc<[T]>>) -> Self {
+        Self {
+            data: AtomicDWord::new(into_data(val.into())),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consumes the atomic and returns the stored slice
+    pub fn into_inner(self) -> Arc<[T]> {
+        // `self` must not be dropped as its reference is handed over
+        let this = ManuallyDrop::new(self);
+        unsafe {
+            drop(ptr::read(&this.reclaim));
+            from_data(ptr::read(&this.data).into_inner())
+        }
+    }
+
+    /// Clones the slice packed in `data`, whose reference is owned by `self`
+    unsafe fn clone_stored(data: DWord) -> Arc<[T]> {
+        let ptr = ManuallyDrop::new(from_data::<T>(data));
+        Arc::clone(&ptr)
+    }
+
+    /// Releases the slice packed in the box at `boxed`
+    unsafe fn release(boxed: usize) {
+        let data = Box::from_raw(boxed as *mut DWord);
+        drop(from_data::<T>(*data))
+    }
+
+    /// Releases the slice packed in `data`, which was just replaced in `self`, once no
+    /// thread is loading it
+    unsafe fn retire(&self, data: DWord) {
+        if self.reclaim.is_quiescent() {
+            return drop(from_data::<T>(data))
+        }
+        // a retired value is a single word, so the two words are boxed
+        self.reclaim.retire(Box::into_raw(Box::new(data)) as usize, Self::release)
+    }
+
+    /// Takes over the reference of the slice packed in `data`, which was just replaced
+    /// in `self`
+    ///
+    /// Another thread may still be loading the slice, so a new reference is returned
+    /// in that case, and the one of `self` is retired.
+    unsafe fn take_replaced(&self, data: DWord) -> Arc<[T]> {
+        if self.reclaim.is_quiescent() {
+            return from_data(data)
+        }
+        let arc = Self::clone_stored(data);
+        self.retire(data);
+        arc
+    }
+
+    /// Runs `cas`, a compare-and-swap on the stored slice
+    ///
+    /// On success, the reference of the replaced slice is taken over. On failure, a new
+    /// reference to the stored slice is returned.
+    fn run_cas(&self, cas: impl FnOnce(&AtomicDWord) -> Result<DWord, DWord>) -> Result<Arc<[T]>, Arc<[T]>> {
+        let result = self.reclaim.protect(|| {
+            // the slice found is cloned before a concurrent store can release it
+            cas(&self.data).map_err(|actual| unsafe { Self::clone_stored(actual) })
+        });
+        // takes over the reference that was held by `self`
+        result.map(|old| unsafe { self.take_replaced(old) })
+    }
+}
+
+impl<T> Atomic for AtomicArcSlice<T> {
+    type Elem = Arc<[T]>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> Arc<[T]> {
+        assert_load_ordering(order);
+        // no slice replaced in the meantime is released before it is cloned
+        self.reclaim.protect(|| unsafe { Self::clone_stored(self.data.load(order)) })
+    }
+
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<Arc<[T]>>, order: Ordering) {
+        assert_store_ordering(order);
+        let old = self.data.swap(into_data(val.into()), order);
+        // releases the reference that was held by `self`
+        unsafe { self.retire(old) }
+    }
+
+    /// Stores a slice into the atomic pointer, returning the previously stored slice
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation.
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<Arc<[T]>>, order: Ordering) -> Arc<[T]> {
+        let old = self.data.swap(into_data(val.into()), order);
+        // takes over the reference that was held by `self`
+        unsafe { self.take_replaced(old) }
+    }
+
+    /// Stores a slice into the atomic pointer if the stored slice is the same allocation
+    /// as `current`.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. `current` is only used for the comparison, and `new` is dropped if
+    /// it is not written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<Arc<[T]>>,
+        new: impl Into<Arc<[T]>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<[T]>, Arc<[T]>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: Arc<[T]> = current.into();
+        let expected = pack(Arc::as_ptr(&current) as *const T as usize, current.len());
+        let new = into_data(new.into());
+
+        self.run_cas(|data| data.compare_exchange(expected, new, success, failure))
+            .inspect_err(|_| {
+                // `new` is not stored and must be released
+                drop(unsafe { from_data::<T>(new) });
+            })
+    }
+
+    /// Stores a slice into the atomic pointer if the stored slice is the same allocation
+    /// as `current`.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<Arc<[T]>>,
+        new: impl Into<Arc<[T]>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Arc<[T]>, Arc<[T]>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: Arc<[T]> = current.into();
+        let expected = pack(Arc::as_ptr(&current) as *const T as usize, current.len());
+        let new = into_data(new.into());
+
+        self.run_cas(|data| data.compare_exchange_weak(expected, new, success, failure))
+            .inspect_err(|_| {
+                // `new` is not stored and must be released
+                drop(unsafe { from_data::<T>(new) });
+            })
+    }
+
+    /// Stores a slice into the atomic pointer if the stored slice is the same allocation
+    /// as `current`, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous slice and a new reference to `new`. On failure, which
+    /// may be spurious, returns the current slice and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<Arc<[T]>>,
+        new: Arc<[T]>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Arc<[T]>, Arc<[T]>), (Arc<[T]>, Arc<[T]>)> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: Arc<[T]> = current.into();
+        let expected = pack(Arc::as_ptr(&current) as *const T as usize, current.len());
+        let new = into_data(new);
+
+        let mut installed = None;
+        let result = self.run_cas(|data| {
+            let result = data.compare_exchange_weak(expected, new, success, failure);
+            if result.is_ok() {
+                // `new` is cloned before a concurrent store can release it
+                installed = Some(unsafe { Self::clone_stored(new) });
+            }
+            result
+        });
+        match result {
+            Ok(prev) => Ok((prev, installed.expect("a stored slice must be cloned"))),
+            // `new` is not stored, so its reference is handed back
+            Err(actual) => Err((actual, unsafe { from_data(new) })),
+        }
+    }
+}
+
+impl<T> Drop for AtomicArcSlice<T> {
+    fn drop(&mut self) {
+        // SAFETY: `self` owns one strong reference of the stored slice
+        drop(unsafe { from_data::<T>(*self.data.get_mut()) });
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_slices_of_different_lengths() {
+        let cell = AtomicArcSlice::new(vec![1u32, 2, 3]);
+
+        let prev = cell.swap(Arc::from(vec![4u32; 10]), Ordering::AcqRel);
+        assert_eq!(*prev, [1, 2, 3]);
+        assert_eq!(*cell.load(Ordering::Acquire), [4; 10]);
+
+        let prev = cell.swap(Arc::from(Vec::new()), Ordering::AcqRel);
+        assert_eq!(prev.len(), 10);
+        assert!(cell.into_inner().is_empty());
+    }
+
+    #[test]
+    fn compare_exchange_compares_slice_address() {
+        let first: Arc<[u8]> = Arc::from(&b"first"[..]);
+        let equal: Arc<[u8]> = Arc::from(&b"first"[..]);
+        let cell = AtomicArcSlice::new(first.clone());
+
+        // an equal slice in another allocation does not match
+        let actual = cell
+            .compare_exchange(equal, &b"second"[..], Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert!(Arc::ptr_eq(&actual, &first));
+
+        let prev = cell
+            .compare_exchange(first.clone(), &b"second"[..], Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert!(Arc::ptr_eq(&prev, &first));
+        assert_eq!(&*cell.load(Ordering::Acquire), b"second");
+
+        drop((prev, actual, cell));
+        assert_eq!(Arc::strong_count(&first), 1);
+    }
+
+    #[test]
+    fn concurrent_loads_read_whole_slices() {
+        const STORES: usize = 100;
+
+        let cell = AtomicArcSlice::new(Vec::<usize>::new());
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for len in 1..=STORES {
+                    // every element holds the length, so a torn read is detected
+                    cell.store(vec![len; len], Ordering::Release);
+                }
+            });
+
+            let mut last = 0;
+            while last < STORES {
+                let current = cell.load(Ordering::Acquire);
+                assert!(current.iter().all(|&val| val == current.len()));
+                assert!(current.len() >= last);
+                last = current.len();
+            }
+        });
+    }
+}
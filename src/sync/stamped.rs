@@ -0,0 +1,252 @@
+use core::{marker::PhantomData, mem::ManuallyDrop, sync::atomic::Ordering};
+
+use crate::primitives::Arc;
+
+use super::{assert_failure_ordering, assert_load_ordering, assert_store_ordering, failure_ordering};
+use super::reclaim::Reclaim;
+
+// the pointer is stored in the lower word and the stamp in the upper word
+#[cfg(target_pointer_width = "64")]
+pub(super) type AtomicDWord = portable_atomic::AtomicU128;
+#[cfg(target_pointer_width = "64")]
+pub(super) type DWord = u128;
+
+#[cfg(target_pointer_width = "32")]
+pub(super) type AtomicDWord = portable_atomic::AtomicU64;
+#[cfg(target_pointer_width = "32")]
+pub(super) type DWord = u64;
+
+/// Packs `ptr` into the lower and `high` into the upper word
+pub(super) fn pack(ptr: usize, high: usize) -> DWord {
+    (high as DWord) << usize::BITS | ptr as DWord
+}
+
+/// Splits `data` into its lower and upper word
+pub(super) fn unpack(data: DWord) -> (usize, usize) {
+    (data as usize, (data >> usize::BITS) as usize)
+}
+
+/// An atomic `Arc` pointer paired with a full word stamp
+///
+/// The few tag bits of `AtomicArc` wrap around too quickly to serve as a version
+/// counter. `AtomicArcStamped` stores the pointer together with a `usize` stamp in a
+/// double-word atomic, and every write increments the stamp. `compare_exchange`
+/// compares both the pointer and the stamp, so a value that was replaced and then
+/// stored again (the ABA problem) is not mistaken for the value that was loaded.
+///
+/// The double-word atomic is provided by `portable-atomic`, which uses the native
+/// instructions (e.g. `cmpxchg16b` on x86_64) where they are available and falls
+/// back to a lock otherwise.
+///
+/// As with [`AtomicArc`](super::AtomicArc), a value that is replaced while another
+/// thread is loading it is released once no thread is loading from the cell any more.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::Ordering;
+/// use nolock::sync::AtomicArcStamped;
+///
+/// let cell = AtomicArcStamped::new(1);
+/// let (current, stamp) = cell.load(Ordering::Acquire);
+/// assert!(cell.compare_exchange(&current, stamp, Arc::new(2), Ordering::AcqRel, Ordering::Acquire).is_ok());
+/// assert_eq!(cell.stamp(Ordering::Acquire), stamp + 1);
+/// ```
+pub struct AtomicArcStamped<T> {
+    data: AtomicDWord,
+    // the values replaced while they were being loaded
+    reclaim: Reclaim,
+    _marker: PhantomData<Arc<T>>,
+}
+
+unsafe impl<T: Sync + Send> Send for AtomicArcStamped<T> {}
+unsafe impl<T: Sync + Send> Sync for AtomicArcStamped<T> {}
+
+impl<T> AtomicArcStamped<T> {
+    /// Creates a new `AtomicArcStamped` with a stamp of `0`
+    pub fn new(val: impl Into<Arc<T>>) -> Self {
+        let ptr = Arc::into_raw(val.into()) as usize;
+        Self {
+            data: AtomicDWord::new(pack(ptr, 0)),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Loads the stored value and its stamp
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    #[must_use = "loading a value has no effect other than returning a new reference"]
+    pub fn load(&self, order: Ordering) -> (Arc<T>, usize) {
+        assert_load_ordering(order);
+        // no value replaced in the meantime is released before it is cloned
+        self.reclaim.protect(|| {
+            let (ptr, stamp) = unpack(self.data.load(order));
+            (unsafe { Self::clone_stored(ptr) }, stamp)
+        })
+    }
+
+    /// Loads the stamp without touching the stored value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    pub fn stamp(&self, order: Ordering) -> usize {
+        assert_load_ordering(order);
+        unpack(self.data.load(order)).1
+    }
+
+    /// Stores a value into the pointer and increments the stamp
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    pub fn store(&self, val: impl Into<Arc<T>>, order: Ordering) {
+        assert_store_ordering(order);
+        drop(self.swap(val, order))
+    }
+
+    /// Stores a value into the pointer and increments the stamp, returning the previous
+    /// value and stamp
+    ///
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    #[must_use = "use `store` if the previous value is not needed"]
+    pub fn swap(&self, val: impl Into<Arc<T>>, order: Ordering) -> (Arc<T>, usize) {
+        let new = Arc::into_raw(val.into()) as usize;
+        let old = self.data
+            .fetch_update(order, failure_ordering(order), |data| {
+                let (_, stamp) = unpack(data);
+                Some(pack(new, stamp.wrapping_add(1)))
+            })
+            .expect("the update always succeeds");
+        let (ptr, stamp) = unpack(old);
+        // takes over the reference that was held by `self`
+        (unsafe { self.take_replaced(ptr) }, stamp)
+    }
+
+    /// Stores `new` if the stored value is the same allocation as `current` and the stored
+    /// stamp equals `stamp`. The stamp is incremented on success.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value and stamp. `new` is dropped if it is not written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    #[must_use = "the result tells whether the value was written"]
+    pub fn compare_exchange(
+        &self,
+        current: &Arc<T>,
+        stamp: usize,
+        new: impl Into<Arc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(Arc<T>, usize), (Arc<T>, usize)> {
+        assert_failure_ordering(failure);
+        let expected = pack(Arc::as_ptr(current) as usize, stamp);
+        let new = Arc::into_raw(new.into());
+        let desired = pack(new as usize, stamp.wrapping_add(1));
+
+        let result = self.reclaim.protect(|| {
+            self.data.compare_exchange(expected, desired, success, failure)
+                // the value found is cloned before a concurrent store can release it
+                .map_err(|actual| {
+                    let (ptr, stamp) = unpack(actual);
+                    (unsafe { Self::clone_stored(ptr) }, stamp)
+                })
+        });
+        match result {
+            // takes over the reference that was held by `self`
+            Ok(old) => Ok((unsafe { self.take_replaced(unpack(old).0) }, stamp)),
+            Err(actual) => {
+                // `new` was not written
+                drop(unsafe { Arc::from_raw(new) });
+                Err(actual)
+            },
+        }
+    }
+
+    /// Clones the `Arc` at `ptr` whose reference is owned by `self`
+    unsafe fn clone_stored(ptr: usize) -> Arc<T> {
+        let ptr = ManuallyDrop::new(Arc::from_raw(ptr as *const T));
+        Arc::clone(&ptr)
+    }
+
+    /// Releases the reference of the `Arc` at `ptr`
+    unsafe fn release(ptr: usize) {
+        drop(Arc::from_raw(ptr as *const T))
+    }
+
+    /// Takes over the reference of the `Arc` at `ptr`, which was just replaced in `self`
+    ///
+    /// Another thread may still be loading the value, so a new reference is returned
+    /// in that case, and the one of `self` is retired.
+    unsafe fn take_replaced(&self, ptr: usize) -> Arc<T> {
+        if self.reclaim.is_quiescent() {
+            return Arc::from_raw(ptr as *const T)
+        }
+        let arc = Self::clone_stored(ptr);
+        self.reclaim.retire(ptr, Self::release);
+        arc
+    }
+}
+
+impl<T> Drop for AtomicArcStamped<T> {
+    fn drop(&mut self) {
+        let (ptr, _) = unpack(*self.data.get_mut());
+        drop(unsafe { Arc::from_raw(ptr as *const T) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamped_cas_rejects_aba() {
+        let a = Arc::new(1);
+        let cell = AtomicArcStamped::new(a.clone());
+        let (loaded, stamp) = cell.load(Ordering::Acquire);
+
+        // A -> B -> A
+        drop(cell.swap(Arc::new(2), Ordering::AcqRel));
+        drop(cell.swap(a.clone(), Ordering::AcqRel));
+        let (current, _) = cell.load(Ordering::Acquire);
+        assert!(Arc::ptr_eq(&current, &loaded));
+
+        let (actual, actual_stamp) = cell
+            .compare_exchange(&loaded, stamp, Arc::new(3), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap_err();
+        assert!(Arc::ptr_eq(&actual, &a));
+        assert_eq!(actual_stamp, stamp + 2);
+
+        let (prev, _) = cell
+            .compare_exchange(&loaded, actual_stamp, Arc::new(3), Ordering::AcqRel, Ordering::Acquire)
+            .unwrap();
+        assert!(Arc::ptr_eq(&prev, &a));
+        assert_eq!(cell.stamp(Ordering::Acquire), stamp + 3);
+
+        drop((loaded, current, actual, prev, cell));
+        assert_eq!(Arc::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn store_bumps_stamp_and_releases() {
+        let first = Arc::new(String::from("first"));
+        let cell = AtomicArcStamped::<String>::new(first.clone());
+        assert_eq!(cell.stamp(Ordering::Acquire), 0);
+
+        cell.store(String::from("second"), Ordering::Release);
+        assert_eq!(Arc::strong_count(&first), 1);
+        let (second, stamp) = cell.load(Ordering::Acquire);
+        assert_eq!(*second, "second");
+        assert_eq!(stamp, 1);
+
+        drop(cell);
+        assert_eq!(Arc::strong_count(&second), 1);
+    }
+}
@@ -1,39 +1,32 @@
-use core::fmt;
-use std::{intrinsics::transmute, mem, ptr::NonNull, usize};
-use std::num::NonZeroUsize;
-use std::sync::Arc;
-// use std::marker::PhantomData;
-
-/// Returns a bitmask containing the unused least significant bits of an aligned pointer to `T`.
-#[inline]
-fn low_bits<T>() -> usize {
-    (1 << mem::align_of::<T>().trailing_zeros()) - 1
-}
-
-// /// Panics if the pointer is not properly unaligned.
-// #[allow(dead_code)]
-// #[inline]
-// fn ensure_aligned<T>(raw: usize) {
-//     assert_eq!(raw & low_bits::<T>(), 0, "unaligned pointer");
-// }
-
-/// Given a tagged pointer `data`, returns the same pointer, but tagged with `tag`.
-///
-/// `tag` is truncated to fit into the unused bits of the pointer to `T`.
-#[inline]
-pub(crate) fn compose_tag<T>(data: usize, tag: usize) -> usize {
-    let mask = low_bits::<T>();
-    (data & !mask) | (tag & mask)
-}
+use core::{cmp::Ordering, fmt};
+use core::hash::{Hash, Hasher};
+use core::{mem, mem::ManuallyDrop, ptr::NonNull};
+use core::num::NonZeroUsize;
+use crate::primitives::Arc;
+#[cfg(not(feature = "loom"))]
+use alloc::boxed::Box;
+// use core::marker::PhantomData;
 
-/// Decomposes a tagged pointer `data` into the pointer and the tag.
-#[inline]
-pub(crate) fn decompose_tag<T>(data: usize) -> (usize, usize) {
-    let mask = low_bits::<T>();
-    (data & !mask, data & mask)
-}
+pub(crate) use crate::raw::{compose_tag, decompose_tag, low_bits};
 
 /// Arc pointer that uses the lower unused bits for tagging
+///
+/// A `TaggedArc` owns one strong reference to the pointee, which is released
+/// when the `TaggedArc` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use nolock::sync::TaggedArc;
+///
+/// let ptr = TaggedArc::from_arc_with_tag(Arc::new(7u64), 0b101);
+/// assert_eq!(ptr.tag(), 0b101);
+///
+/// let (arc, tag) = TaggedArc::decompose(ptr);
+/// assert_eq!((*arc, tag), (7, 0b101));
+/// ```
+#[repr(transparent)]
 pub struct TaggedArc<T> {
     // data is a tagged pointer
     pub(crate) data: NonNull<T>,
@@ -50,8 +43,37 @@ impl<T> TaggedArc<T> {
         Self::from_arc(ptr)
     }
 
-    pub fn compose(ptr: Arc<T>, tag: usize) -> Self {
-        let ptr: Arc<T> = ptr.into();
+    /// The number of unused low bits of a pointer to `T` that can hold a tag
+    ///
+    /// Generic code that needs a tag can reject types without any tag bits at
+    /// compile time:
+    ///
+    /// ```
+    /// use nolock::sync::TaggedArc;
+    ///
+    /// fn needs_tag<T>() {
+    ///     const { assert!(TaggedArc::<T>::TAG_BITS > 0, "`T` cannot carry a tag") }
+    /// }
+    ///
+    /// needs_tag::<u32>();
+    /// ```
+    pub const TAG_BITS: u32 = mem::align_of::<T>().trailing_zeros();
+
+    /// Returns the number of unused low bits of a pointer to `T` that can hold a tag
+    pub const fn tag_bits() -> u32 {
+        Self::TAG_BITS
+    }
+
+    /// Returns the largest tag that fits into the unused low bits of a pointer to `T`
+    pub const fn max_tag() -> usize {
+        low_bits::<T>()
+    }
+
+    /// Creates a `TaggedArc` from `ptr` carrying `tag`
+    ///
+    /// The bits of `tag` that do not fit into the unused bits of the pointer, i.e. that
+    /// are above [`TaggedArc::max_tag`], are discarded.
+    pub fn from_arc_with_tag(ptr: Arc<T>, tag: usize) -> Self {
         let raw = Arc::into_raw(ptr) as usize;
         let data = compose_tag::<T>(raw, tag);
         // SAFETY: data is composed from a valid pointer addr and tag
@@ -61,6 +83,21 @@ impl<T> TaggedArc<T> {
         }
     }
 
+    /// Creates a `TaggedArc` from `ptr` carrying `tag`
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` is larger than [`TaggedArc::max_tag`]. In release
+    /// builds, the bits of `tag` that do not fit are discarded.
+    #[deprecated(note = "use `TaggedArc::from_arc_with_tag`")]
+    pub fn compose(ptr: Arc<T>, tag: usize) -> Self {
+        debug_assert!(
+            tag <= Self::max_tag(),
+            "tag {:#x} does not fit into the {} unused bits of the pointer", tag, Self::tag_bits()
+        );
+        Self::from_arc_with_tag(ptr, tag)
+    }
+
     pub fn from_arc(val: Arc<T>) -> Self {
         let raw = Arc::into_raw(val) as *mut T;
 
@@ -71,27 +108,42 @@ impl<T> TaggedArc<T> {
         }
     }
 
+    #[must_use = "dropping the returned `Arc` releases the reference"]
     pub fn into_arc(self) -> Arc<T> {
         // remove tag information
-        let (data, _) = decompose_tag::<Arc<T>>(self.into_usize());
+        let (data, _) = decompose_tag::<T>(self.into_usize());
         unsafe { Arc::from_raw(data as *const T) }
     }
 
+    /// Converts into a plain `Arc`, discarding the tag
+    ///
+    /// This is the same as [`into_arc`](TaggedArc::into_arc).
+    #[must_use = "dropping the returned `Arc` releases the reference"]
+    pub fn strip(self) -> Arc<T> {
+        self.into_arc()
+    }
+
     pub fn decompose(ptr: TaggedArc<T>) -> (Arc<T>, usize) {
-        let (data, tag) = decompose_tag::<Arc<T>>(
-            // SAFETY: only valid pointers will be stored
-            unsafe { transmute::<NonNull<T>, usize>(ptr.data) }    
-        );
+        let (data, tag) = decompose_tag::<T>(ptr.into_usize());
         let ptr = data as *const T;
         unsafe {
             (Arc::from_raw(ptr), tag)
         }
     }
 
+    /// Returns a new `Arc` to the value and the tag, leaving `self` intact
+    ///
+    /// Unlike [`decompose`](TaggedArc::decompose), the strong count is incremented,
+    /// so the returned `Arc` and `self` each own a reference.
+    pub fn decompose_ref(&self) -> (Arc<T>, usize) {
+        TaggedArc::decompose(self.clone_raw())
+    }
+
+    #[must_use = "the reference is leaked if the returned address is discarded"]
     pub fn into_usize(self) -> usize {
-        unsafe {
-            transmute(self.data)
-        }
+        // the strong reference is handed over to the returned address
+        let ptr = ManuallyDrop::new(self);
+        ptr.data.as_ptr() as usize
     }
 
     /// # Safety
@@ -100,39 +152,120 @@ impl<T> TaggedArc<T> {
     pub unsafe fn from_usize(data: usize) -> Option<Self> {
         let data = NonZeroUsize::new(data)?;
         let ret = Self {
-            data: transmute(data)
+            data: NonNull::new_unchecked(data.get() as *mut T)
         };
         Some(ret)
     }
 
+    /// Takes over the strong reference at the tagged address `data`, checking that the
+    /// pointer part is non-null and aligned like the value of an `Arc`, which follows
+    /// the reference counts and is aligned at least like `usize`
+    ///
+    /// # Safety
+    ///
+    /// If the checks pass, `data` must have been obtained from [`into_usize`], and the
+    /// caller must own the strong reference it carries. The checks only catch some
+    /// invalid addresses.
+    ///
+    /// [`into_usize`]: TaggedArc::into_usize
+    pub unsafe fn try_from_usize(data: usize) -> Result<Self, TaggedArcError> {
+        let (ptr, _) = decompose_tag::<T>(data);
+        if ptr == 0 {
+            Err(TaggedArcError::Null)
+        } else if ptr % mem::align_of::<usize>() != 0 {
+            Err(TaggedArcError::Misaligned)
+        } else {
+            Ok(Self {
+                data: NonNull::new_unchecked(data as *mut T)
+            })
+        }
+    }
+
     pub fn as_raw(&self) -> *const T {
-        let (data, _) = decompose_tag::<Arc<T>>(
-            unsafe { transmute::<NonNull<T>, usize>(self.data) }
+        let (data, _) = decompose_tag::<T>(
+            self.data.as_ptr() as usize
         );
         data as *const T
     }
 
+    /// # Safety
+    /// 
+    /// `raw` must be obtained from a valid `Arc` pointer
     pub unsafe fn from_raw(raw: *const T) -> Option<Self> {
         let data = raw as usize;
         Self::from_usize(data)
     }
 
+    #[must_use = "the reference is leaked if the returned pointer is discarded"]
     pub fn into_raw(ptr: TaggedArc<T>) -> *const T {
+        let ptr = ManuallyDrop::new(ptr);
         ptr.as_raw()
     }
 
     pub fn tag(&self) -> usize {
-        let (_, tag) = decompose_tag::<Arc<T>>(
-            unsafe { transmute::<NonNull<T>, usize>(self.data) }
+        let (_, tag) = decompose_tag::<T>(
+            self.data.as_ptr() as usize
         );
         tag
     }
 
+    /// Returns `true` if both pointers point to the same allocation, ignoring the tags,
+    /// in a similar way to [`Arc::ptr_eq`](alloc::sync::Arc::ptr_eq)
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.as_raw() == other.as_raw()
+    }
+
+    /// Returns a new pointer to the same value carrying the same tag
+    ///
+    /// The strong count is incremented directly on the untagged pointer, and the
+    /// tagged address is copied as is. This is what [`Clone`] does.
+    pub fn clone_raw(&self) -> Self {
+        // SAFETY: `self` holds a strong reference, so the pointer is valid
+        unsafe { Arc::increment_strong_count(self.as_raw()) };
+        Self {
+            data: self.data,
+        }
+    }
+
+    /// Reinterprets the pointer as a pointer to `U`, keeping the tag
+    ///
+    /// # Safety
+    ///
+    /// The pointer is released as an `Arc<U>`, so the requirements of
+    /// [`Arc::from_raw`](alloc::sync::Arc::from_raw) apply: `U` must have the same
+    /// size and alignment as `T`, and the value must be valid as a `U`. The same
+    /// alignment also ensures that the tag still fits into the unused bits of the pointer.
+    pub unsafe fn cast<U>(self) -> TaggedArc<U> {
+        TaggedArc {
+            data: ManuallyDrop::new(self).data.cast(),
+        }
+    }
+
+    /// Returns `true` if `self` and `other` point to the same allocation, ignoring the tags
+    ///
+    /// This is the method form of [`TaggedArc::ptr_eq`].
+    pub fn eq_ignore_tag(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other)
+    }
+
+    /// Returns a new pointer to the same value carrying `tag`
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` is larger than [`TaggedArc::max_tag`]. In release
+    /// builds, the bits of `tag` that do not fit are discarded.
     pub fn with_tag(&self, tag: usize) -> Self {
+        debug_assert!(
+            tag <= Self::max_tag(),
+            "tag {:#x} does not fit into the {} unused bits of the pointer", tag, Self::tag_bits()
+        );
+        // the returned pointer needs its own strong reference
+        let ptr = ManuallyDrop::new(self.clone());
+
         // `compose_tag` will take care of removing any old tag
         // that is already with the current self.data
         let data = compose_tag::<T>(
-            unsafe { transmute(self.data) }, 
+            ptr.data.as_ptr() as usize, 
             tag
         );
 
@@ -142,6 +275,83 @@ impl<T> TaggedArc<T> {
             data,
         }
     }
+
+    /// Returns a new pointer to the same value whose tag is `f` applied to the current tag
+    ///
+    /// The bits of the new tag that do not fit into the pointer are discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nolock::sync::TaggedArc;
+    ///
+    /// let ptr = TaggedArc::from_arc_with_tag(std::sync::Arc::new(1u64), 0b10);
+    /// assert_eq!(ptr.map_tag(|t| t ^ 0b1).tag(), 0b11);
+    /// ```
+    pub fn map_tag(&self, f: impl FnOnce(usize) -> usize) -> Self {
+        self.with_tag(f(self.tag()) & low_bits::<T>())
+    }
+
+    /// Returns a new pointer to the same value whose tag is incremented by one
+    ///
+    /// The tag wraps around from [`TaggedArc::max_tag`] to zero.
+    pub fn incr_tag(&self) -> Self {
+        self.map_tag(|tag| tag.wrapping_add(1))
+    }
+
+    /// Returns a new pointer to the same value whose tag is decremented by one
+    ///
+    /// The tag wraps around from zero to [`TaggedArc::max_tag`].
+    pub fn decr_tag(&self) -> Self {
+        self.map_tag(|tag| tag.wrapping_sub(1))
+    }
+
+    /// Replaces the tag of `self` with `f` applied to the current tag
+    ///
+    /// The bits of the new tag that do not fit into the pointer are discarded.
+    pub fn update_tag(&mut self, f: impl FnOnce(usize) -> usize) {
+        let tag = f(self.tag()) & low_bits::<T>();
+        self.with_tag_mut(tag);
+    }
+
+    /// Replaces the tag of `self` in place, without touching the reference count
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` is larger than [`TaggedArc::max_tag`]. In release
+    /// builds, the bits of `tag` that do not fit are discarded.
+    pub fn with_tag_mut(&mut self, tag: usize) {
+        debug_assert!(
+            tag <= Self::max_tag(),
+            "tag {:#x} does not fit into the {} unused bits of the pointer", tag, Self::tag_bits()
+        );
+        let data = compose_tag::<T>(self.data.as_ptr() as usize, tag);
+        // SAFETY: only the tag of a valid pointer is replaced
+        self.data = unsafe { NonNull::new_unchecked(data as *mut T) };
+    }
+}
+
+impl<T: Clone> TaggedArc<T> {
+    /// Returns a mutable reference to the value, cloning it first if it is shared
+    ///
+    /// This is like [`Arc::make_mut`](alloc::sync::Arc::make_mut) for a tagged pointer. The
+    /// tag is kept when the value is cloned into a new allocation. Unlike `Arc::make_mut`,
+    /// a value that is only shared with `Weak` pointers is cloned as well.
+    pub fn make_mut(&mut self) -> &mut T {
+        let tag = self.tag();
+        // the reference is still owned by `self`
+        let mut arc = ManuallyDrop::new(unsafe { Arc::from_raw(self.as_raw()) });
+        if Arc::get_mut(&mut arc).is_none() {
+            // releases the reference of `self`
+            *arc = Arc::new(T::clone(&arc));
+        }
+        let raw = Arc::into_raw(ManuallyDrop::into_inner(arc));
+        let data = compose_tag::<T>(raw as usize, tag);
+        // SAFETY: `data` is composed from a valid pointer and tag, and `self` takes over
+        // the reference of the unique `Arc`
+        self.data = unsafe { NonNull::new_unchecked(data as *mut T) };
+        unsafe { &mut *(raw as *mut T) }
+    }
 }
 
 impl<T> From<Arc<T>> for TaggedArc<T> {
@@ -150,44 +360,161 @@ impl<T> From<Arc<T>> for TaggedArc<T> {
     }
 }
 
+/// Moves the boxed value into a new `TaggedArc` without a tag.
+///
+/// The conversion goes through `Arc::from`, which reuses the allocation of the `Box`.
+#[cfg(not(feature = "loom"))]
+impl<T> From<Box<T>> for TaggedArc<T> {
+    fn from(val: Box<T>) -> Self {
+        Self::from_arc(Arc::from(val))
+    }
+}
+
 impl<T> From<TaggedArc<T>> for Arc<T> {
     fn from(ptr: TaggedArc<T>) -> Self {
         ptr.into_arc()
     }
 }
 
+impl<T> AsRef<T> for TaggedArc<T> {
+    /// Borrows the pointee
+    fn as_ref(&self) -> &T {
+        // SAFETY: the tag is masked out and `self` holds a strong reference
+        // for as long as the returned borrow lives
+        unsafe { &*self.as_raw() }
+    }
+}
+
+impl<T> core::ops::Deref for TaggedArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.as_ref()
+    }
+}
+
 impl<T> Clone for TaggedArc<T> {
     fn clone(&self) -> Self {
-        let (data, tag) = decompose_tag::<Arc<T>>(
-            unsafe { transmute::<NonNull<T>, usize>(self.data) }
-        );
-        let ptr = unsafe { Arc::from_raw(data as *const T) };
-        let new = Arc::clone(&ptr);
-        TaggedArc::new(new)
-            .with_tag(tag)
+        self.clone_raw()
+    }
+}
+
+/// Two `TaggedArc`s are equal if they point to the same allocation and carry the
+/// same tag.
+///
+/// This is identity equality and does not compare the pointed-to values, so it does
+/// not require `T: PartialEq`. Use [`TaggedArc::ptr_eq`] to ignore the tag.
+///
+/// For the same reason `TaggedArc<T>` does not implement `Borrow<T>`, whose contract
+/// requires equality and hashing to agree with those of `T`. A map keyed by
+/// `TaggedArc` is looked up by pointer, so use [`AsRef`] or `Deref` to reach the
+/// pointee instead.
+impl<T> PartialEq for TaggedArc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl<T> Eq for TaggedArc<T> {}
+
+/// Orders by pointer address, and by tag for the same address.
+///
+/// This is an ordering of the allocations, not of the pointed-to values, so it does
+/// not require `T: Ord`. It is arbitrary but stable while the pointers are alive,
+/// which gives a canonical order, e.g. for iterating a `BTreeSet` of nodes.
+impl<T> PartialOrd for TaggedArc<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for TaggedArc<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the tag is stored in the low bits, below the aligned address
+        self.data.cmp(&other.data)
+    }
+}
+
+/// Hashes the pointer address together with the tag, consistent with `PartialEq`.
+impl<T> Hash for TaggedArc<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state)
     }
 }
 
 impl<T: fmt::Debug> fmt::Debug for TaggedArc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (data, _) = decompose_tag::<Arc<T>>(
-            unsafe { transmute::<NonNull<T>, usize>(self.data) }
+        let (data, _) = decompose_tag::<T>(
+            self.data.as_ptr() as usize
         );       
-        let ptr = unsafe { Arc::from_raw(data as *const T) };
-        fmt::Debug::fmt(&ptr, f) 
+        let ptr = ManuallyDrop::new(unsafe { Arc::from_raw(data as *const T) });
+        fmt::Debug::fmt(&*ptr, f) 
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for TaggedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (data, _) = decompose_tag::<T>(
+            self.data.as_ptr() as usize
+        );
+        // the tag is left out of the output
+        let ptr = ManuallyDrop::new(unsafe { Arc::from_raw(data as *const T) });
+        fmt::Display::fmt(&**ptr, f)
     }
 }
 
-// impl<T> Drop for TaggedArc<T> {
-//     fn drop(&mut self) {
-//         let (data, _) = decompose_tag::<T>(self.data.get());
-//         // ERROR: this will incur memory SIGSEV
-//         let ptr = unsafe { Arc::from_raw(data as *const T) };        // manually call drop on Arc ptr
-//         drop(ptr);
-//     }
-// }
+/// The error returned by [`TaggedArc::try_from_usize`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaggedArcError {
+    /// The pointer part of the address is null
+    Null,
+    /// The pointer part of the address is not aligned like the value of an `Arc`
+    Misaligned,
+}
 
-#[cfg(test)]
+impl fmt::Display for TaggedArcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaggedArcError::Null => f.write_str("null pointer"),
+            TaggedArcError::Misaligned => f.write_str("misaligned pointer"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TaggedArcError {}
+
+/// Serializes the pointed-to value. The tag is not serialized.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for TaggedArc<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (data, _) = decompose_tag::<T>(
+            self.data.as_ptr() as usize
+        );
+        let ptr = ManuallyDrop::new(unsafe { Arc::from_raw(data as *const T) });
+        T::serialize(&**ptr, serializer)
+    }
+}
+
+/// Deserializes the value into a new `Arc` with a tag of 0.
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for TaggedArc<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(|val| Self::from_arc(Arc::new(val)))
+    }
+}
+
+impl<T> Drop for TaggedArc<T> {
+    fn drop(&mut self) {
+        let (data, _) = decompose_tag::<T>(
+            self.data.as_ptr() as usize
+        );
+        // SAFETY: `self` owns one strong reference of the pointer
+        drop(unsafe { Arc::from_raw(data as *const T) });
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
 mod tests {
     #![allow(dead_code, unused_imports)]
     use std::{mem::{size_of_val, transmute}, ptr::NonNull, sync::atomic::AtomicUsize};
@@ -222,6 +549,7 @@ mod tests {
         println!("align: {:?}", &align);
         let mask = low_bits::<Examining>();
         println!("low bits mask of Arc<&str>: {:?}", mask);
+        drop(unsafe { Arc::from_raw(raw) });
     }
 
     #[test]
@@ -257,6 +585,7 @@ mod tests {
         println!("tag1: 0x{:x}", &tag1);
         assert_eq!(raw, raw1);
         assert_eq!(tag, tag1);
+        drop(unsafe { Arc::from_raw(raw1 as *const i32) });
     }
 
     #[cfg(feature = "tag")]
@@ -301,7 +630,7 @@ mod tests {
 
             let mut a = 1;
             println!("{:p}", &a);
-            a = a + 1;
+            a += 1;
             println!("{:p}", &a);            
 
             // let raw2 = transmute::<&AtomicUsize, &*const i32>(&data);
@@ -338,7 +667,7 @@ mod tests {
         // println!("[2] 0x{:x}", &comp);
         // let comp = comp | (tag & mask);
         // let comp = compose_tag::<Arc<i32>>(data, tag);
-        let comp = TaggedArc::compose(ptr.clone(), tag);
+        let comp = TaggedArc::from_arc_with_tag(ptr.clone(), tag);
         println!("[3] 0x{:p}", &comp);
 
         // PROBLEM: two Arcs are constructed and one of them will
@@ -379,8 +708,8 @@ mod tests {
     #[test]
     fn test_size_of_ptrs() {
         let val = "12313231312321";
-        let arc_ptr = Arc::new(val.clone());
-        let box_ptr = Box::new(val.clone());
+        let arc_ptr = Arc::new(val);
+        let box_ptr = Box::new(val);
         
         println!("size(Arc) {:?}", size_of_val(&arc_ptr));
         println!("size(Box) {:?}", size_of_val(&box_ptr));
@@ -389,5 +718,407 @@ mod tests {
         let raw_box = Box::into_raw(box_ptr);
         println!("size(raw Arc) {:?}", size_of_val(&raw_arc));
         println!("size(raw Box) {:?}", size_of_val(&raw_box));
+        unsafe {
+            drop(Arc::from_raw(raw_arc));
+            drop(Box::from_raw(raw_box));
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn low_bits_of_arc_i64() {
+        assert_eq!(low_bits::<Arc<i64>>(), 0b111);
+        assert_eq!(low_bits::<i64>(), 0b111);
+    }
+
+    #[test]
+    fn eq_compares_pointer_and_tag() {
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(1u64), 0b01);
+        assert_eq!(ptr, ptr.clone());
+        assert_eq!(ptr, ptr.with_tag(0b01));
+
+        let retagged = ptr.with_tag(0b10);
+        assert_ne!(ptr, retagged);
+        assert!(TaggedArc::ptr_eq(&ptr, &retagged));
+
+        // equal values in different allocations are not equal pointers
+        let other = TaggedArc::from_arc_with_tag(Arc::new(1u64), 0b01);
+        assert_ne!(ptr, other);
+        assert!(!TaggedArc::ptr_eq(&ptr, &other));
+    }
+
+    #[test]
+    fn hash_set_collapses_equal_pointers() {
+        use std::collections::HashSet;
+
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(5u64), 0b01);
+        let mut set = HashSet::new();
+        set.insert(ptr.clone());
+        set.insert(ptr.clone());
+        set.insert(ptr.with_tag(0b01));
+        assert_eq!(set.len(), 1);
+
+        set.insert(ptr.with_tag(0b10));
+        set.insert(TaggedArc::from_arc_with_tag(Arc::new(5u64), 0b01));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&ptr));
+    }
+
+    #[repr(align(16))]
+    struct Align16(#[allow(dead_code)] u8);
+
+    #[test]
+    fn tag_capacity() {
+        assert_eq!(TaggedArc::<u8>::tag_bits(), 0);
+        assert_eq!(TaggedArc::<u8>::max_tag(), 0);
+        assert_eq!(TaggedArc::<u32>::tag_bits(), 2);
+        assert_eq!(TaggedArc::<u32>::max_tag(), 0b11);
+        assert_eq!(TaggedArc::<Align16>::tag_bits(), 4);
+        assert_eq!(TaggedArc::<Align16>::max_tag(), 0b1111);
+    }
+
+    #[test]
+    fn max_tag_fits() {
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(0u8), TaggedArc::<u8>::max_tag());
+        assert_eq!(ptr.tag(), 0);
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(0u32), TaggedArc::<u32>::max_tag());
+        assert_eq!(ptr.tag(), 0b11);
+        assert_eq!(ptr.with_tag(0b01).tag(), 0b01);
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(Align16(0)), 0b111);
+        assert_eq!(ptr.tag(), 0b111);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "does not fit")]
+    #[allow(deprecated)]
+    fn compose_rejects_tag_on_u8() {
+        TaggedArc::compose(Arc::new(0u8), 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "does not fit")]
+    fn with_tag_rejects_oversized_tag() {
+        let ptr = TaggedArc::new(0u32);
+        ptr.with_tag(0b100);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "does not fit")]
+    #[allow(deprecated)]
+    fn compose_rejects_oversized_tag_on_align16() {
+        TaggedArc::compose(Arc::new(Align16(0)), 0b10000);
+    }
+
+    #[test]
+    fn from_arc_with_tag_truncates_tag() {
+        let arc = Arc::new(0u32);
+        let ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b1110);
+        assert_eq!(ptr.tag(), 0b10);
+        assert_eq!(ptr.as_raw(), Arc::as_ptr(&arc));
+        assert_eq!(*ptr, 0);
+    }
+
+    #[test]
+    fn from_box_round_trip() {
+        let mut data = [0u8; 1024];
+        data.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        let ptr = TaggedArc::from(Box::new(data));
+        assert_eq!(ptr.tag(), 0);
+        let arc = ptr.into_arc();
+        assert_eq!(*arc, data);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn map_tag_toggles_and_increments() {
+        let arc = Arc::new(1u64);
+        let ptr = TaggedArc::from_arc(arc.clone());
+
+        let toggled = ptr.map_tag(|t| t ^ 0b1);
+        assert_eq!(toggled.tag(), 0b1);
+        assert_eq!(toggled.map_tag(|t| t ^ 0b1).tag(), 0);
+        assert!(TaggedArc::ptr_eq(&toggled, &ptr));
+        assert_eq!(toggled.as_raw(), Arc::as_ptr(&arc));
+
+        let mut counter = ptr.clone();
+        for expected in 1..=TaggedArc::<u64>::max_tag() {
+            counter.update_tag(|t| t + 1);
+            assert_eq!(counter.tag(), expected);
+        }
+        // the overflowing bits are discarded
+        counter.update_tag(|t| t + 1);
+        assert_eq!(counter.tag(), 0);
+        assert_eq!(counter.as_raw(), Arc::as_ptr(&arc));
+
+        drop((ptr, toggled, counter));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn with_tag_mut_keeps_strong_count() {
+        let arc = Arc::new(2u64);
+        let mut ptr = TaggedArc::from_arc(arc.clone());
+        assert_eq!(Arc::strong_count(&arc), 2);
+
+        ptr.with_tag_mut(0b110);
+        assert_eq!(ptr.tag(), 0b110);
+        assert_eq!(ptr.as_raw(), Arc::as_ptr(&arc));
+        ptr.with_tag_mut(0b001);
+        assert_eq!(ptr.tag(), 0b001);
+        assert_eq!(Arc::strong_count(&arc), 2);
+
+        drop(ptr);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn clone_raw_keeps_tag_and_counts() {
+        let arc = Arc::new(4u64);
+        let ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b101);
+
+        let raw = ptr.clone_raw();
+        assert_eq!(raw, ptr);
+        assert_eq!(raw.tag(), 0b101);
+        assert_eq!(Arc::strong_count(&arc), 3);
+
+        // the same count as cloning through an `Arc`
+        let through_arc = TaggedArc::from_arc_with_tag(Arc::clone(&arc), ptr.tag());
+        assert_eq!(Arc::strong_count(&arc), 4);
+        assert_eq!(through_arc, raw);
+
+        drop((ptr, raw, through_arc));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn incr_and_decr_tag_wrap_around() {
+        let arc = Arc::new(8u64);
+        let max = TaggedArc::from_arc_with_tag(arc.clone(), TaggedArc::<u64>::max_tag());
+
+        let wrapped = max.incr_tag();
+        assert_eq!(wrapped.tag(), 0);
+        assert_eq!(wrapped.as_raw(), Arc::as_ptr(&arc));
+        assert_eq!(wrapped.decr_tag().tag(), TaggedArc::<u64>::max_tag());
+        assert_eq!(wrapped.incr_tag().incr_tag().tag(), 2);
+        assert_eq!(max.decr_tag().tag(), TaggedArc::<u64>::max_tag() - 1);
+    }
+
+    #[test]
+    fn cast_round_trip_keeps_tag() {
+        let arc = Arc::new(-1i64);
+        let ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b11);
+
+        let unsigned = unsafe { ptr.cast::<u64>() };
+        assert_eq!(unsigned.tag(), 0b11);
+        assert_eq!(*unsigned, u64::MAX);
+
+        let signed = unsafe { unsigned.cast::<i64>() };
+        assert_eq!(signed.tag(), 0b11);
+        assert_eq!(signed.as_raw(), Arc::as_ptr(&arc));
+        assert_eq!(Arc::strong_count(&arc), 2);
+        drop(signed);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn display_omits_tag() {
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(42i32), 0b1);
+        assert_eq!(ptr.to_string(), "42");
+        assert_eq!(format!("{:>4}", ptr), "  42");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_drops_tag() {
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(vec![1u32, 2, 3]), 0b11);
+        let json = serde_json::to_string(&ptr).unwrap();
+        assert_eq!(json, "[1,2,3]");
+
+        let ptr: TaggedArc<Vec<u32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(ptr.tag(), 0);
+        assert_eq!(*ptr.into_arc(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn deref_reads_field() {
+        struct Point {
+            x: i64,
+            y: i64,
+        }
+
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(Point { x: 3, y: -4 }), 0b111);
+        assert_eq!(ptr.x, 3);
+        assert_eq!(ptr.as_ref().y, -4);
+        assert_eq!(ptr.tag(), 0b111);
+    }
+
+    #[test]
+    fn ptr_eq_ignores_tags() {
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(5u64), 0b001);
+        let clone = ptr.clone();
+        assert!(TaggedArc::ptr_eq(&ptr, &clone));
+
+        let retagged = clone.with_tag(0b110);
+        assert!(TaggedArc::ptr_eq(&ptr, &retagged));
+        assert!(TaggedArc::ptr_eq(&retagged, &ptr));
+
+        let other = TaggedArc::from_arc_with_tag(Arc::new(5u64), 0b001);
+        assert!(!TaggedArc::ptr_eq(&ptr, &other));
+        assert!(!TaggedArc::ptr_eq(&other.with_tag(0b110), &retagged));
+    }
+
+    #[test]
+    fn map_keys_compare_by_identity() {
+        use std::collections::HashMap;
+
+        let key: TaggedArc<String> = TaggedArc::new(String::from("key"));
+        let mut map = HashMap::new();
+        map.insert(key.clone(), 1u32);
+
+        // an equal string in another allocation is a different key
+        assert_eq!(map.get(&key), Some(&1));
+        assert_eq!(map.get(&TaggedArc::from_arc(Arc::new(String::from("key")))), None);
+        assert_eq!(map.keys().next().map(|k| k.as_str()), Some("key"));
+    }
+
+    #[test]
+    fn btree_set_orders_by_address() {
+        use std::collections::BTreeSet;
+
+        let arcs: Vec<_> = (0..8u64).map(Arc::new).collect();
+        let set: BTreeSet<_> = arcs.iter()
+            .rev()
+            .map(|arc| TaggedArc::from_arc(arc.clone()))
+            .collect();
+        assert_eq!(set.len(), arcs.len());
+
+        let addrs: Vec<_> = set.iter().map(|ptr| ptr.as_raw() as usize).collect();
+        let mut sorted = addrs.clone();
+        sorted.sort_unstable();
+        assert_eq!(addrs, sorted);
+
+        // the same allocation with different tags is ordered by tag
+        let first = set.iter().next().unwrap();
+        let tagged = first.with_tag(0b1);
+        assert!(first.eq_ignore_tag(&tagged));
+        assert_ne!(first, &tagged);
+        assert!(first < &tagged);
+    }
+
+    #[test]
+    fn decompose_counts() {
+        let arc = Arc::new(6u64);
+
+        let ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b11);
+        let (borrowed, tag) = ptr.decompose_ref();
+        assert_eq!(tag, 0b11);
+        assert!(Arc::ptr_eq(&borrowed, &arc));
+        assert_eq!(Arc::strong_count(&arc), 3);
+        assert_eq!(ptr.tag(), 0b11);
+        assert_eq!(*ptr, 6);
+
+        // the reference held by `ptr` is handed over
+        let (owned, tag) = TaggedArc::decompose(ptr);
+        assert_eq!(tag, 0b11);
+        assert_eq!(Arc::strong_count(&arc), 3);
+
+        drop((borrowed, owned));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn try_from_usize_validates_address() {
+        assert_eq!(unsafe { TaggedArc::<u64>::try_from_usize(0) }, Err(TaggedArcError::Null));
+        // a tag without a pointer
+        assert_eq!(unsafe { TaggedArc::<u64>::try_from_usize(0b11) }, Err(TaggedArcError::Null));
+        // `u8` leaves no bits for a tag, and the value of an `Arc` is aligned like `usize`
+        assert_eq!(
+            unsafe { TaggedArc::<u8>::try_from_usize(0x1001) }.map(TaggedArc::into_usize),
+            Err(TaggedArcError::Misaligned)
+        );
+
+        let arc = Arc::new(2u64);
+        let data = TaggedArc::from_arc_with_tag(arc.clone(), 0b11).into_usize();
+        let ptr = unsafe { TaggedArc::<u64>::try_from_usize(data) }.unwrap();
+        assert_eq!(ptr.tag(), 0b11);
+        assert_eq!(*ptr, 2);
+        drop(ptr);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn over_aligned_values_keep_wide_tags() {
+        #[repr(align(16))]
+        struct Align16(u64);
+
+        let arc = Arc::new(Align16(7));
+        let ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b1011);
+        assert_eq!(TaggedArc::<Align16>::max_tag(), 0b1111);
+        assert_eq!(ptr.tag(), 0b1011);
+        assert_eq!(ptr.as_raw(), Arc::as_ptr(&arc));
+        assert_eq!(ptr.0, 7);
+
+        let (out, tag) = TaggedArc::decompose(ptr.with_tag(0b1110));
+        assert!(Arc::ptr_eq(&out, &arc));
+        assert_eq!(tag, 0b1110);
+        assert!(Arc::ptr_eq(&ptr.into_arc(), &arc));
+        drop(out);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn strip_discards_tag() {
+        let arc = Arc::new(1u64);
+        let out = TaggedArc::from_arc_with_tag(arc.clone(), 0b11).strip();
+        assert!(Arc::ptr_eq(&out, &arc));
+        assert_eq!(Arc::strong_count(&arc), 2);
+    }
+
+    #[test]
+    fn make_mut_clones_shared_value() {
+        let arc = Arc::new(vec![1]);
+        let mut ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b11);
+
+        ptr.make_mut().push(2);
+        assert_eq!(*arc, [1]);
+        assert_eq!(*ptr, [1, 2]);
+        assert_eq!(ptr.tag(), 0b11);
+        assert_eq!(Arc::strong_count(&arc), 1);
+
+        // unique now, so it is mutated in place
+        let raw = ptr.as_raw();
+        ptr.make_mut().push(3);
+        assert_eq!(ptr.as_raw(), raw);
+        assert_eq!(*ptr, [1, 2, 3]);
+    }
+
+    #[test]
+    fn zero_sized_values() {
+        // `()` has no tag bits, so the tag is discarded
+        let unit = Arc::new(());
+        let ptr = TaggedArc::from_arc_with_tag(unit.clone(), 0b1);
+        assert_eq!(ptr.tag(), 0);
+        let cloned = ptr.clone();
+        assert!(TaggedArc::ptr_eq(&ptr, &cloned));
+        assert_eq!(Arc::strong_count(&unit), 3);
+        drop((ptr, cloned));
+        assert_eq!(Arc::strong_count(&unit), 1);
+
+        // a zero-sized type can still be aligned
+        let empty = Arc::new([0u64; 0]);
+        let ptr = TaggedArc::from_arc_with_tag(empty.clone(), 0b101);
+        assert_eq!(ptr.tag(), 0b101);
+        assert_eq!(ptr.as_raw(), Arc::as_ptr(&empty));
+        let cloned = ptr.with_tag(0b10);
+        assert_eq!(cloned.tag(), 0b10);
+        assert_eq!(Arc::strong_count(&empty), 3);
+        let (arc, tag) = TaggedArc::decompose(ptr);
+        assert_eq!(tag, 0b101);
+        assert!(Arc::ptr_eq(&arc, &empty));
+        drop((arc, cloned));
+        assert_eq!(Arc::strong_count(&empty), 1);
+    }
+}
@@ -0,0 +1,190 @@
+use core::{fmt, mem, ops::Deref, ptr::NonNull};
+
+use crate::primitives::Arc;
+
+use super::low_bits;
+
+/// Returns the mask of the tag bits of a pointer into an `Arc` allocation
+///
+/// The value of an `Arc` is stored after the reference counts, so the pointer to
+/// it is aligned at least like `usize`, whatever the type of the value is.
+const fn tag_mask() -> usize {
+    low_bits::<usize>()
+}
+
+/// Arc pointer to a possibly unsized value that uses the lower unused bits for tagging
+///
+/// A pointer to `dyn Trait` or `[T]` is a wide pointer made of the data pointer and the
+/// metadata, such as a vtable, so it does not fit into the single word of a
+/// [`TaggedArc`](super::TaggedArc). `TaggedArcDyn` takes two words: the data pointer
+/// carries the tag on its unused lower bits, and the metadata is kept unchanged next to it.
+///
+/// The tag bits are those of a pointer to `usize`, because the alignment of the value
+/// behind a `dyn Trait` is not known statically.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use nolock::sync::TaggedArcDyn;
+///
+/// let f: Arc<dyn Fn() -> i32> = Arc::new(|| 7);
+/// let ptr = TaggedArcDyn::from_arc_with_tag(f, 0b1);
+/// assert_eq!(ptr.tag(), 0b1);
+/// assert_eq!(ptr(), 7);
+/// ```
+pub struct TaggedArcDyn<T: ?Sized> {
+    // the wide pointer whose data pointer is tagged
+    ptr: NonNull<T>,
+}
+
+unsafe impl<T: ?Sized + Sync + Send> Send for TaggedArcDyn<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for TaggedArcDyn<T> {}
+
+impl<T: ?Sized> TaggedArcDyn<T> {
+    /// Returns the largest tag that fits into the unused low bits of the data pointer
+    pub const fn max_tag() -> usize {
+        tag_mask()
+    }
+
+    pub fn from_arc(ptr: Arc<T>) -> Self {
+        Self::from_arc_with_tag(ptr, 0)
+    }
+
+    /// Creates a `TaggedArcDyn` from `ptr` carrying `tag`
+    ///
+    /// The bits of `tag` that do not fit into the unused bits of the pointer are discarded.
+    pub fn from_arc_with_tag(ptr: Arc<T>, tag: usize) -> Self {
+        let raw = Arc::into_raw(ptr) as *mut T;
+        Self {
+            // SAFETY: the data pointer of an `Arc` is aligned, so adding a tag
+            // smaller than the alignment keeps it non-null
+            ptr: unsafe { NonNull::new_unchecked(raw.wrapping_byte_add(tag & tag_mask())) },
+        }
+    }
+
+    pub fn tag(&self) -> usize {
+        self.ptr.as_ptr() as *const u8 as usize & tag_mask()
+    }
+
+    /// Returns the pointer to the value without the tag
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr().wrapping_byte_sub(self.tag())
+    }
+
+    /// Replaces the tag, leaving the pointer untouched
+    ///
+    /// The bits of `tag` that do not fit into the unused bits of the pointer are discarded.
+    pub fn set_tag(&mut self, tag: usize) {
+        let raw = self.as_ptr() as *mut T;
+        // SAFETY: only the tag of a valid pointer is replaced
+        self.ptr = unsafe { NonNull::new_unchecked(raw.wrapping_byte_add(tag & tag_mask())) };
+    }
+
+    /// Returns a new pointer to the same value carrying `tag`
+    pub fn with_tag(&self, tag: usize) -> Self {
+        let mut ptr = self.clone();
+        ptr.set_tag(tag);
+        ptr
+    }
+
+    /// Converts into a plain `Arc`, discarding the tag
+    #[must_use = "dropping the returned `Arc` releases the reference"]
+    pub fn into_arc(self) -> Arc<T> {
+        let ptr = mem::ManuallyDrop::new(self);
+        // SAFETY: the reference held by `self` is handed over
+        unsafe { Arc::from_raw(ptr.as_ptr()) }
+    }
+
+    /// Returns `true` if both pointers point to the same allocation, ignoring the tags
+    /// and the metadata
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.as_ptr() as *const u8 == other.as_ptr() as *const u8
+    }
+}
+
+impl<T: ?Sized> Deref for TaggedArcDyn<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: `self` holds a strong reference, so the value is alive
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T: ?Sized> Clone for TaggedArcDyn<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: `self` holds a strong reference, so the pointer is valid
+        unsafe { Arc::increment_strong_count(self.as_ptr()) };
+        Self {
+            ptr: self.ptr,
+        }
+    }
+}
+
+impl<T: ?Sized> From<Arc<T>> for TaggedArcDyn<T> {
+    fn from(ptr: Arc<T>) -> Self {
+        Self::from_arc(ptr)
+    }
+}
+
+impl<T: ?Sized> From<TaggedArcDyn<T>> for Arc<T> {
+    fn from(ptr: TaggedArcDyn<T>) -> Self {
+        ptr.into_arc()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for TaggedArcDyn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Drop for TaggedArcDyn<T> {
+    fn drop(&mut self) {
+        // SAFETY: `self` owns one strong reference
+        drop(unsafe { Arc::from_raw(self.as_ptr()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_and_loads_closures() {
+        let first: Arc<dyn Fn() -> i32> = Arc::new(|| 1);
+        let offset = 10;
+        let second: Arc<dyn Fn() -> i32> = Arc::new(move || offset + 2);
+
+        let ptrs = vec![
+            TaggedArcDyn::from_arc_with_tag(first.clone(), 0b1),
+            TaggedArcDyn::from_arc_with_tag(second.clone(), 0b10),
+        ];
+        let calls: Vec<_> = ptrs.iter().map(|ptr| (ptr(), ptr.tag())).collect();
+        assert_eq!(calls, [(1, 0b1), (12, 0b10)]);
+
+        let retagged = ptrs[0].with_tag(0b11);
+        assert_eq!(retagged.tag(), 0b11);
+        assert!(TaggedArcDyn::ptr_eq(&retagged, &ptrs[0]));
+        assert_eq!(Arc::strong_count(&first), 3);
+
+        let arc = retagged.into_arc();
+        assert!(Arc::ptr_eq(&arc, &first));
+        assert_eq!(arc(), 1);
+
+        drop((ptrs, arc));
+        assert_eq!(Arc::strong_count(&first), 1);
+        assert_eq!(Arc::strong_count(&second), 1);
+    }
+
+    #[test]
+    fn tags_slices() {
+        let slice: Arc<[u8]> = Arc::from(&b"abc"[..]);
+        let mut ptr = TaggedArcDyn::from_arc(slice.clone());
+        ptr.set_tag(TaggedArcDyn::<[u8]>::max_tag());
+        assert_eq!(ptr.tag(), TaggedArcDyn::<[u8]>::max_tag());
+        assert_eq!(&*ptr, b"abc");
+        assert_eq!(ptr.len(), 3);
+    }
+}
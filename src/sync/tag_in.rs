@@ -0,0 +1,191 @@
+use alloc::sync::Arc;
+use core::{alloc::Allocator, fmt, mem::ManuallyDrop, ops::Deref, ptr, ptr::NonNull};
+
+use super::{compose_tag, decompose_tag, low_bits};
+
+/// Arc pointer placed in a custom allocator that uses the lower unused bits for tagging
+///
+/// This is the allocator-aware counterpart of [`TaggedArc`](super::TaggedArc). The tag
+/// only touches the pointer address, so the allocator is kept next to the tagged pointer
+/// and handed back to the `Arc` whenever the reference is released.
+///
+/// Only available on nightly with `feature = "allocator_api"`.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+/// use std::alloc::Global;
+/// use nolock::sync::TaggedArcIn;
+///
+/// let ptr = TaggedArcIn::new_in(7u64, Global).with_tag(0b101);
+/// assert_eq!((*ptr, ptr.tag()), (7, 0b101));
+/// ```
+pub struct TaggedArcIn<T, A: Allocator> {
+    // data is a tagged pointer
+    data: NonNull<T>,
+    alloc: A,
+}
+
+unsafe impl<T: Sync + Send, A: Allocator + Send> Send for TaggedArcIn<T, A> {}
+unsafe impl<T: Sync + Send, A: Allocator + Sync> Sync for TaggedArcIn<T, A> {}
+
+impl<T, A: Allocator> TaggedArcIn<T, A> {
+    /// Allocates `val` in `alloc` with a zero tag
+    pub fn new_in(val: T, alloc: A) -> Self {
+        Self::from_arc(Arc::new_in(val, alloc))
+    }
+
+    pub fn from_arc(ptr: Arc<T, A>) -> Self {
+        Self::from_arc_with_tag(ptr, 0)
+    }
+
+    /// Creates a `TaggedArcIn` from `ptr` carrying `tag`
+    ///
+    /// The bits of `tag` that do not fit into the unused bits of the pointer are discarded.
+    pub fn from_arc_with_tag(ptr: Arc<T, A>, tag: usize) -> Self {
+        let (raw, alloc) = Arc::into_raw_with_allocator(ptr);
+        let data = compose_tag::<T>(raw as usize, tag);
+        Self {
+            // SAFETY: the pointer of an `Arc` is never null
+            data: unsafe { NonNull::new_unchecked(data as *mut T) },
+            alloc,
+        }
+    }
+
+    /// Returns the largest tag that fits into the unused low bits of the pointer
+    pub const fn max_tag() -> usize {
+        low_bits::<T>()
+    }
+
+    pub fn tag(&self) -> usize {
+        decompose_tag::<T>(self.data.as_ptr() as usize).1
+    }
+
+    /// Returns the pointer to the value without the tag
+    pub fn as_raw(&self) -> *const T {
+        decompose_tag::<T>(self.data.as_ptr() as usize).0 as *const T
+    }
+
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Replaces the tag, leaving the pointer untouched
+    pub fn set_tag(&mut self, tag: usize) {
+        let data = compose_tag::<T>(self.data.as_ptr() as usize, tag);
+        // SAFETY: only the tag of a non-null pointer is replaced
+        self.data = unsafe { NonNull::new_unchecked(data as *mut T) };
+    }
+
+    /// Returns the pointer carrying `tag` instead
+    pub fn with_tag(mut self, tag: usize) -> Self {
+        self.set_tag(tag);
+        self
+    }
+
+    /// Converts into an `Arc` in the same allocator, discarding the tag
+    #[must_use = "dropping the returned `Arc` releases the reference"]
+    pub fn into_arc(self) -> Arc<T, A> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is not dropped, so the allocator is moved out only once
+        let alloc = unsafe { ptr::read(&this.alloc) };
+        // SAFETY: the reference held by `self` is handed over
+        unsafe { Arc::from_raw_in(this.as_raw(), alloc) }
+    }
+
+    /// Returns `true` if both pointers point to the same allocation, ignoring the tags
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.as_raw() == other.as_raw()
+    }
+}
+
+impl<T, A: Allocator> Deref for TaggedArcIn<T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: `self` holds a strong reference, so the value is alive
+        unsafe { &*self.as_raw() }
+    }
+}
+
+impl<T, A: Allocator + Clone> Clone for TaggedArcIn<T, A> {
+    fn clone(&self) -> Self {
+        // SAFETY: `self` holds a strong reference, so the pointer is valid
+        unsafe { Arc::increment_strong_count_in(self.as_raw(), self.alloc.clone()) };
+        Self {
+            data: self.data,
+            alloc: self.alloc.clone(),
+        }
+    }
+}
+
+impl<T, A: Allocator> From<Arc<T, A>> for TaggedArcIn<T, A> {
+    fn from(ptr: Arc<T, A>) -> Self {
+        Self::from_arc(ptr)
+    }
+}
+
+impl<T, A: Allocator> From<TaggedArcIn<T, A>> for Arc<T, A> {
+    fn from(ptr: TaggedArcIn<T, A>) -> Self {
+        ptr.into_arc()
+    }
+}
+
+impl<T: fmt::Debug, A: Allocator> fmt::Debug for TaggedArcIn<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T, A: Allocator> Drop for TaggedArcIn<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: `self` is being dropped, so the allocator is moved out only once
+        let alloc = unsafe { ptr::read(&self.alloc) };
+        // SAFETY: `self` owns one strong reference of the pointer
+        drop(unsafe { Arc::from_raw_in(self.as_raw(), alloc) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{AllocError, Global, Layout};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Counts the live allocations made through it
+    #[derive(Clone)]
+    struct Counting<'a> {
+        live: &'a AtomicUsize,
+    }
+
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.live.fetch_add(1, Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.live.fetch_sub(1, Ordering::Relaxed);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn allocates_in_custom_allocator() {
+        let live = AtomicUsize::new(0);
+        let ptr = TaggedArcIn::new_in(7u64, Counting { live: &live }).with_tag(0b101);
+        assert_eq!(live.load(Ordering::Relaxed), 1);
+        assert_eq!((*ptr, ptr.tag()), (7, 0b101));
+
+        let clone = ptr.clone();
+        assert!(TaggedArcIn::ptr_eq(&ptr, &clone));
+        assert_eq!(live.load(Ordering::Relaxed), 1);
+
+        let arc = clone.into_arc();
+        assert_eq!(Arc::strong_count(&arc), 2);
+        drop((ptr, arc));
+        assert_eq!(live.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,469 @@
+use core::{fmt, marker::PhantomData, mem::ManuallyDrop, sync::atomic::Ordering};
+use crate::primitives::{Arc, AtomicUsize, Weak};
+
+use super::{Atomic, TaggedArc, assert_failure_ordering, assert_load_ordering, assert_store_ordering};
+use super::{compose_tag, decompose_tag, low_bits};
+use super::reclaim::Reclaim;
+
+/// Weak pointer that uses the lower unused bits for tagging
+///
+/// A `TaggedWeak` owns one weak reference to the pointee, which is released when
+/// the `TaggedWeak` is dropped. A dangling `Weak`, such as the one returned by
+/// `Weak::new`, has no allocation and is stored as the null address, so it can
+/// still carry a tag.
+pub struct TaggedWeak<T> {
+    // data is a tagged pointer, whose pointer part is zero if the `Weak` is dangling
+    data: usize,
+    _marker: PhantomData<Weak<T>>,
+}
+
+unsafe impl<T: Sync + Send> Send for TaggedWeak<T> {}
+unsafe impl<T: Sync + Send> Sync for TaggedWeak<T> {}
+
+impl<T> TaggedWeak<T> {
+    pub fn from_weak(weak: Weak<T>) -> Self {
+        Self::compose(weak, 0)
+    }
+
+    /// Creates a `TaggedWeak` from `weak` carrying `tag`
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` is larger than [`TaggedArc::max_tag`]. In release
+    /// builds, the bits of `tag` that do not fit are discarded.
+    pub fn compose(weak: Weak<T>, tag: usize) -> Self {
+        debug_assert!(
+            tag <= low_bits::<T>(),
+            "tag {:#x} does not fit into the {} unused bits of the pointer", tag, TaggedArc::<T>::tag_bits()
+        );
+        // the sentinel address of a dangling `Weak` is not aligned, so it cannot be tagged
+        let raw = if Weak::ptr_eq(&weak, &Weak::new()) {
+            0
+        } else {
+            Weak::into_raw(weak) as usize
+        };
+        Self {
+            data: compose_tag::<T>(raw, tag),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new weak pointer to the value of `ptr`, carrying the tag of `ptr`
+    pub fn downgrade(ptr: &TaggedArc<T>) -> Self {
+        // the reconstructed `Arc` only borrows the reference held by `ptr`
+        let arc = ManuallyDrop::new(unsafe { Arc::from_raw(ptr.as_raw()) });
+        Self::compose(Arc::downgrade(&arc), ptr.tag())
+    }
+
+    pub fn into_weak(self) -> Weak<T> {
+        let (raw, _) = decompose_tag::<T>(self.into_usize());
+        unsafe { Self::weak_from_raw(raw) }
+    }
+
+    #[must_use = "the weak reference is leaked if the returned address is discarded"]
+    pub fn into_usize(self) -> usize {
+        // the weak reference is handed over to the returned address
+        let ptr = ManuallyDrop::new(self);
+        ptr.data
+    }
+
+    /// # Safety
+    ///
+    /// `data` must be obtained from [`TaggedWeak::into_usize`]
+    pub unsafe fn from_usize(data: usize) -> Self {
+        Self {
+            data,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn tag(&self) -> usize {
+        let (_, tag) = decompose_tag::<T>(self.data);
+        tag
+    }
+
+    /// Returns a new weak pointer to the same value carrying `tag`
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `tag` is larger than [`TaggedArc::max_tag`]. In release
+    /// builds, the bits of `tag` that do not fit are discarded.
+    pub fn with_tag(&self, tag: usize) -> Self {
+        let weak = Weak::clone(&self.as_weak());
+        Self::compose(weak, tag)
+    }
+
+    /// Attempts to upgrade to a `TaggedArc`, which carries the same tag as `self`
+    ///
+    /// Returns `None` if the `Weak` is dangling or if the value has already been
+    /// dropped.
+    pub fn upgrade(&self) -> Option<TaggedArc<T>> {
+        let arc = self.as_weak().upgrade()?;
+        Some(TaggedArc::from_arc_with_tag(arc, self.tag()))
+    }
+
+    /// Reconstructs the `Weak` without taking over the reference held by `self`
+    fn as_weak(&self) -> ManuallyDrop<Weak<T>> {
+        let (raw, _) = decompose_tag::<T>(self.data);
+        ManuallyDrop::new(unsafe { Self::weak_from_raw(raw) })
+    }
+
+    /// # Safety
+    ///
+    /// `raw` must be zero or obtained from `Weak::into_raw`
+    unsafe fn weak_from_raw(raw: usize) -> Weak<T> {
+        if raw == 0 {
+            Weak::new()
+        } else {
+            Weak::from_raw(raw as *const T)
+        }
+    }
+}
+
+impl<T> TaggedArc<T> {
+    /// Creates a new weak pointer to the value of `this`, carrying the tag of `this`
+    ///
+    /// This is an associated function like [`Arc::downgrade`], so that it does not
+    /// shadow a method of `T`.
+    pub fn downgrade(this: &Self) -> TaggedWeak<T> {
+        TaggedWeak::downgrade(this)
+    }
+}
+
+impl<T> From<Weak<T>> for TaggedWeak<T> {
+    fn from(weak: Weak<T>) -> Self {
+        Self::from_weak(weak)
+    }
+}
+
+impl<T> Clone for TaggedWeak<T> {
+    fn clone(&self) -> Self {
+        self.with_tag(self.tag())
+    }
+}
+
+impl<T> fmt::Debug for TaggedWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(TaggedWeak)")
+    }
+}
+
+impl<T> Drop for TaggedWeak<T> {
+    fn drop(&mut self) {
+        let (raw, _) = decompose_tag::<T>(self.data);
+        // SAFETY: `self` owns one weak reference of the pointer
+        drop(unsafe { Self::weak_from_raw(raw) });
+    }
+}
+
+/// A `TaggedWeak` that can be shared and updated across threads
+///
+/// As with [`AtomicArc`](super::AtomicArc), a value that is replaced while another
+/// thread is loading it is released once no thread is loading from the cell any more.
+pub struct AtomicWeak<T> {
+    // data is a usize that contains the pointer and the tag of a `TaggedWeak`
+    data: AtomicUsize,
+    // the values replaced while they were being loaded
+    reclaim: Reclaim,
+    _marker: PhantomData<Weak<T>>,
+}
+
+unsafe impl<T: Sync + Send> Send for AtomicWeak<T> {}
+unsafe impl<T: Sync + Send> Sync for AtomicWeak<T> {}
+
+impl<T> AtomicWeak<T> {
+    pub fn new(val: impl Into<TaggedWeak<T>>) -> Self {
+        let ptr: TaggedWeak<T> = val.into();
+        Self {
+            data: AtomicUsize::new(ptr.into_usize()),
+            reclaim: Reclaim::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a new reference to the pointer `data`, which is still held by `self`
+    unsafe fn clone_stored(data: usize) -> TaggedWeak<T> {
+        let ptr = ManuallyDrop::new(TaggedWeak::from_usize(data));
+        TaggedWeak::clone(&ptr)
+    }
+
+    /// Releases the weak reference held by the pointer `data`
+    unsafe fn release(data: usize) {
+        drop(TaggedWeak::<T>::from_usize(data))
+    }
+
+    /// Takes over the reference of the pointer `data`, which was just replaced in `self`
+    ///
+    /// Another thread may still be loading the value, so a new reference is returned
+    /// in that case, and the one of `self` is retired.
+    unsafe fn take_replaced(&self, data: usize) -> TaggedWeak<T> {
+        if self.reclaim.is_quiescent() {
+            return TaggedWeak::from_usize(data)
+        }
+        let ptr = Self::clone_stored(data);
+        self.reclaim.retire(data, Self::release);
+        ptr
+    }
+
+    /// Runs `cas`, a compare-and-swap on the stored pointer.
+    ///
+    /// On success, the reference of the replaced value is taken over. On failure, a new
+    /// reference to the stored value is returned, which is taken before a concurrent
+    /// store can release the value.
+    fn run_cas(
+        &self,
+        cas: impl FnOnce(&AtomicUsize) -> Result<usize, usize>,
+    ) -> Result<TaggedWeak<T>, TaggedWeak<T>> {
+        let result = self.reclaim.protect(|| {
+            // SAFETY: the pointer was found in `self`
+            cas(&self.data).map_err(|err| unsafe { Self::clone_stored(err) })
+        });
+        result.map(|ok| unsafe { self.take_replaced(ok) })
+    }
+}
+
+impl<T> Atomic for AtomicWeak<T> {
+    type Elem = TaggedWeak<T>;
+
+    /// Loads a value from the atomic pointer.
+    ///
+    /// `load` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Acquire` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`.
+    fn load(&self, order: Ordering) -> TaggedWeak<T> {
+        assert_load_ordering(order);
+        // no value replaced in the meantime is released before it is cloned
+        self.reclaim.protect(|| {
+            let data = self.data.load(order);
+            // clone because `load` does not give away ownership
+            unsafe { Self::clone_stored(data) }
+        })
+    }
+
+    /// Stores a value into the pointer
+    ///
+    /// `store` takes an `Ordering` argument which describes
+    /// the memory ordering of this operation.
+    /// Possible values are `SeqCst`, `Release` and `Relaxed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Acquire` or `AcqRel`.
+    fn store(&self, val: impl Into<TaggedWeak<T>>, order: Ordering) {
+        assert_store_ordering(order);
+        let ptr: TaggedWeak<T> = val.into();
+        let old_data = self.data.swap(ptr.into_usize(), order);
+        // the previous value must be released once it is replaced
+        unsafe { self.reclaim.retire(old_data, Self::release) }
+    }
+
+    /// Stores a `TaggedWeak` pointer into the atomic pointer, returning the previously stored pointer
+    ///
+    /// swap takes an `Ordering` argument which describes the memory ordering of this operation.
+    /// All ordering modes are possible. Note that using `Acquire` makes the store part of this
+    /// operation `Relaxed`, and using `Release` makes the load part `Relaxed`.
+    fn swap(&self, val: impl Into<TaggedWeak<T>>, order: Ordering) -> TaggedWeak<T> {
+        let ptr: TaggedWeak<T> = val.into();
+        let old_data = self.data.swap(ptr.into_usize(), order);
+        unsafe { self.take_replaced(old_data) }
+    }
+
+    /// Stores a `TaggedWeak` pointer into the atomic pointer if the current value is the same
+    /// as the `current` value. The tag will also be compared.
+    ///
+    /// The return value is a result indicating whether the new value was written and containing
+    /// the previous value. On success this value is guaranteed to be equal to `current`.
+    /// `current` is only used for the comparison, and `new` is dropped if it is not written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange(
+        &self,
+        current: impl Into<TaggedWeak<T>>,
+        new: impl Into<TaggedWeak<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedWeak<T>, TaggedWeak<T>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: TaggedWeak<T> = current.into();
+        let new: TaggedWeak<T> = new.into();
+        let new = new.into_usize();
+
+        self.run_cas(|data| data.compare_exchange(current.data, new, success, failure))
+            .inspect_err(|_| {
+                // `new` is not stored and must be released
+                drop(unsafe { TaggedWeak::<T>::from_usize(new) });
+            })
+    }
+
+    /// Stores a `TaggedWeak` pointer into the atomic pointer if the current value is the same
+    /// as the `current` value.
+    ///
+    /// Unlike [`compare_exchange`], this function is allowed to spuriously fail even when the
+    /// comparison succeeds, which can result in more efficient code on some platforms.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    ///
+    /// [`compare_exchange`]: Atomic::compare_exchange
+    fn compare_exchange_weak(
+        &self,
+        current: impl Into<TaggedWeak<T>>,
+        new: impl Into<TaggedWeak<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<TaggedWeak<T>, TaggedWeak<T>> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: TaggedWeak<T> = current.into();
+        let new: TaggedWeak<T> = new.into();
+        let new = new.into_usize();
+
+        self.run_cas(|data| data.compare_exchange_weak(current.data, new, success, failure))
+            .inspect_err(|_| {
+                // `new` is not stored and must be released
+                drop(unsafe { TaggedWeak::<T>::from_usize(new) });
+            })
+    }
+
+    /// Stores a `TaggedWeak` pointer into the atomic pointer if the current value is the same
+    /// as the `current` value, handing `new` back if it is not written.
+    ///
+    /// On success, returns the previous value and a new reference to `new`. On failure, which
+    /// may be spurious, returns the current value and `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure` is `Release` or `AcqRel`.
+    fn compare_exchange_weak_keep(
+        &self,
+        current: impl Into<TaggedWeak<T>>,
+        new: TaggedWeak<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(TaggedWeak<T>, TaggedWeak<T>), (TaggedWeak<T>, TaggedWeak<T>)> {
+        assert_failure_ordering(failure);
+        // `current` is only used for comparison and released afterwards
+        let current: TaggedWeak<T> = current.into();
+        let new = new.into_usize();
+
+        let mut installed = None;
+        let result = self.run_cas(|data| {
+            let result = data.compare_exchange_weak(current.data, new, success, failure);
+            if result.is_ok() {
+                // `new` is cloned before a concurrent store can release it
+                installed = Some(unsafe { Self::clone_stored(new) });
+            }
+            result
+        });
+        match result {
+            Ok(prev) => Ok((prev, installed.expect("a stored value must be cloned"))),
+            // `new` is not stored, so its reference is handed back
+            Err(actual) => Err((actual, unsafe { TaggedWeak::from_usize(new) })),
+        }
+    }
+}
+
+impl<T> Drop for AtomicWeak<T> {
+    fn drop(&mut self) {
+        // `&mut self` guarantees that no other thread is accessing the pointer
+        let data = self.data.load(Ordering::Relaxed);
+        // SAFETY: `self` owns one weak reference of the stored pointer
+        drop(unsafe { TaggedWeak::<T>::from_usize(data) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_fails_after_last_strong_reference() {
+        let arc = Arc::new(3u64);
+        let weak = TaggedWeak::compose(Arc::downgrade(&arc), 0b10);
+        assert_eq!(weak.tag(), 0b10);
+
+        let strong = weak.upgrade().unwrap();
+        assert_eq!(strong.tag(), 0b10);
+        assert_eq!(*strong.into_arc(), 3);
+
+        drop(arc);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn dangling_weak_keeps_tag() {
+        let weak = TaggedWeak::<u64>::compose(Weak::new(), 0b11);
+        assert_eq!(weak.tag(), 0b11);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.with_tag(0b01).tag(), 0b01);
+        assert!(weak.into_weak().upgrade().is_none());
+    }
+
+    #[test]
+    fn downgrade_counts_weak_references() {
+        let arc = Arc::new(7u64);
+        let ptr = TaggedArc::from_arc_with_tag(arc.clone(), 0b1);
+        let weak = TaggedWeak::downgrade(&ptr);
+        assert_eq!(weak.tag(), 0b1);
+        assert_eq!(Arc::weak_count(&arc), 1);
+
+        let copy = weak.clone();
+        assert_eq!(Arc::weak_count(&arc), 2);
+        drop(copy);
+        drop(weak);
+        assert_eq!(Arc::weak_count(&arc), 0);
+        assert_eq!(Arc::strong_count(&arc), 2);
+    }
+
+    #[test]
+    fn tagged_arc_downgrade_round_trip() {
+        let ptr = TaggedArc::from_arc_with_tag(Arc::new(5u64), 0b110);
+        let weak = TaggedArc::downgrade(&ptr);
+        assert_eq!(weak.tag(), 0b110);
+
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(upgraded.tag(), 0b110);
+        assert!(TaggedArc::ptr_eq(&upgraded, &ptr));
+        assert_eq!(upgraded.as_raw(), ptr.as_raw());
+
+        drop((upgraded, ptr));
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn atomic_weak_operations() {
+        let a = Arc::new(1u64);
+        let b = Arc::new(2u64);
+        let cell = AtomicWeak::new(Arc::downgrade(&a));
+
+        let current = cell.load(Ordering::Acquire);
+        assert!(Arc::ptr_eq(&current.upgrade().unwrap().into_arc(), &a));
+
+        let out = cell.compare_exchange(Arc::downgrade(&b), Arc::downgrade(&b), Ordering::AcqRel, Ordering::Acquire);
+        assert!(out.is_err());
+        drop(out);
+        assert_eq!(Arc::weak_count(&b), 0);
+
+        let out = cell.compare_exchange(current, Arc::downgrade(&b), Ordering::AcqRel, Ordering::Acquire);
+        assert!(out.is_ok());
+        drop(out);
+        assert_eq!(Arc::weak_count(&a), 0);
+        assert_eq!(Arc::weak_count(&b), 1);
+
+        cell.store(Weak::new(), Ordering::Release);
+        assert_eq!(Arc::weak_count(&b), 0);
+        assert!(cell.load(Ordering::Acquire).upgrade().is_none());
+
+        cell.store(Arc::downgrade(&b), Ordering::Release);
+        drop(b);
+        assert!(cell.load(Ordering::Acquire).upgrade().is_none());
+    }
+}
@@ -0,0 +1,38 @@
+//! Checks that `AtomicArc::fetch_update_dedup` skips the exchange for an unchanged
+//! pointer, by counting exchanges with the observer of `feature = "test-hooks"`.
+//!
+//! The observer is global, so this runs in its own test binary.
+#![cfg(all(feature = "test-hooks", not(feature = "loom")))]
+// the conversions to `Arc` are needed when `feature = "tag"` is enabled
+#![cfg_attr(not(feature = "tag"), allow(clippy::useless_conversion))]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use nolock::sync::hooks::{clear_cas_observer, set_cas_id, set_cas_observer};
+use nolock::sync::{Atomic, AtomicArc};
+
+#[test]
+fn unchanged_pointer_issues_no_exchange() {
+    static EXCHANGES: AtomicUsize = AtomicUsize::new(0);
+    set_cas_observer(|_, _| {
+        EXCHANGES.fetch_add(1, Ordering::Relaxed);
+    });
+    set_cas_id(1);
+
+    let cell = AtomicArc::new(1);
+    let prev = cell
+        .fetch_update_dedup(Ordering::AcqRel, Ordering::Acquire, |current| Some(current.clone()))
+        .unwrap();
+    assert_eq!(*prev, 1);
+    assert_eq!(EXCHANGES.load(Ordering::Relaxed), 0);
+
+    let prev = cell
+        .fetch_update_dedup(Ordering::AcqRel, Ordering::Acquire, |_| Some(Arc::new(2).into()))
+        .unwrap();
+    assert_eq!(*prev, 1);
+    assert_eq!(EXCHANGES.load(Ordering::Relaxed), 1);
+    assert_eq!(*cell.load(Ordering::Acquire), 2);
+
+    clear_cas_observer();
+}
@@ -0,0 +1,128 @@
+//! Hands values from a producer thread to a consumer thread through the atomic
+//! pointers, with or without `feature = "tag"`.
+//!
+//! The payload counts its drops and poisons itself when dropped, so a value that
+//! is observed after it was freed, released twice or leaked fails the tests.
+#![cfg(not(feature = "loom"))]
+// the conversions to `Arc` are needed when `feature = "tag"` is enabled
+#![cfg_attr(not(feature = "tag"), allow(clippy::useless_conversion))]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+
+use nolock::sync::{Atomic, AtomicArc, AtomicOptionArc};
+
+const VALUES: u64 = 1000;
+const POISON: u64 = u64::MAX;
+
+struct Payload {
+    seq: AtomicU64,
+    drops: Arc<AtomicUsize>,
+}
+
+impl Payload {
+    fn new(seq: u64, drops: &Arc<AtomicUsize>) -> Arc<Self> {
+        Arc::new(Self {
+            seq: AtomicU64::new(seq),
+            drops: drops.clone(),
+        })
+    }
+
+    /// Returns the sequence number, checking that the payload is still alive
+    fn seq(&self) -> u64 {
+        let seq = self.seq.load(Ordering::Relaxed);
+        assert_ne!(seq, POISON, "observed a payload after it was dropped");
+        seq
+    }
+}
+
+impl Drop for Payload {
+    fn drop(&mut self) {
+        assert_ne!(*self.seq.get_mut(), POISON, "payload dropped twice");
+        *self.seq.get_mut() = POISON;
+        self.drops.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn consumer_loads_stored_sequence() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let cell = Arc::new(AtomicArc::from_arc(Payload::new(0, &drops)));
+
+    let producer = {
+        let (cell, drops) = (cell.clone(), drops.clone());
+        thread::spawn(move || {
+            for seq in 1..=VALUES {
+                cell.store(Payload::new(seq, &drops), Ordering::Release);
+            }
+        })
+    };
+    let consumer = {
+        let cell = cell.clone();
+        thread::spawn(move || {
+            let mut seen = Vec::new();
+            loop {
+                let current: Arc<Payload> = cell.load(Ordering::Acquire).into();
+                let seq = current.seq();
+                if seen.last() != Some(&seq) {
+                    seen.push(seq);
+                }
+                if seq == VALUES {
+                    return seen
+                }
+            }
+        })
+    };
+
+    producer.join().unwrap();
+    let seen = consumer.join().unwrap();
+    assert!(seen.windows(2).all(|pair| pair[0] < pair[1]), "values observed out of order");
+
+    // every replaced value has been released, and the last one is still stored
+    assert_eq!(drops.load(Ordering::Relaxed), VALUES as usize);
+    drop(cell);
+    assert_eq!(drops.load(Ordering::Relaxed), VALUES as usize + 1);
+}
+
+#[test]
+fn consumer_takes_every_value_once() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let slot = Arc::new(AtomicOptionArc::<Payload>::new(None));
+
+    let producer = {
+        let (slot, drops) = (slot.clone(), drops.clone());
+        thread::spawn(move || {
+            for seq in 0..VALUES {
+                let mut next = Payload::new(seq, &drops);
+                // waits for the consumer to empty the slot
+                while let Err(rejected) = slot.set_if_none(next) {
+                    next = rejected;
+                    thread::yield_now();
+                }
+            }
+        })
+    };
+    let consumer = {
+        let slot = slot.clone();
+        thread::spawn(move || {
+            let mut taken = Vec::new();
+            while taken.len() < VALUES as usize {
+                match slot.take(Ordering::AcqRel) {
+                    Some(payload) => {
+                        let payload: Arc<Payload> = payload.into();
+                        taken.push(payload.seq());
+                    },
+                    None => thread::yield_now(),
+                }
+            }
+            taken
+        })
+    };
+
+    producer.join().unwrap();
+    let taken = consumer.join().unwrap();
+    assert_eq!(taken, (0..VALUES).collect::<Vec<_>>());
+    assert!(slot.load(Ordering::Acquire).is_none());
+    assert_eq!(drops.load(Ordering::Relaxed), VALUES as usize);
+}
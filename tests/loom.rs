@@ -0,0 +1,142 @@
+//! Model checks of the atomic operations, run with `cargo test --features loom --test loom`.
+//!
+//! The doc-tests are written against `std::sync::Arc` and do not build with `feature = "loom"`.
+//!
+//! `AtomicArc::load` reads the pointer before taking a new reference, so a value that
+//! is replaced in between must stay alive until the reference is taken. The models store
+//! fresh values that only the cell refers to, and `loom` fails an execution in which a
+//! released value is loaded or a value is never released.
+#![cfg(feature = "loom")]
+
+use loom::sync::Arc;
+use loom::sync::atomic::Ordering;
+use loom::thread;
+
+#[cfg(all(feature = "collections", feature = "std"))]
+use nolock::set::Set;
+#[cfg(feature = "collections")]
+use nolock::stack::Stack;
+use nolock::sync::{Atomic, AtomicArc};
+
+/// Checks `f` with a bounded number of preemptions per execution
+///
+/// Every load and exchange also updates the readers of the cell, and the unbounded
+/// models take minutes to explore.
+fn model<F: Fn() + Sync + Send + 'static>(f: F) {
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound = Some(3);
+    builder.check(f);
+}
+
+/// Reads the value behind a loaded pointer, with or without `feature = "tag"`
+fn value(ptr: impl Into<Arc<i32>>) -> i32 {
+    *ptr.into()
+}
+
+#[test]
+fn racing_compare_exchange() {
+    model(|| {
+        let first = Arc::new(0);
+        let atomic = Arc::new(AtomicArc::from_arc(first.clone()));
+
+        let handles: Vec<_> = (1..=2)
+            .map(|i| {
+                let atomic = atomic.clone();
+                let first = first.clone();
+                thread::spawn(move || {
+                    atomic.compare_exchange(first, Arc::new(i), Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                })
+            })
+            .collect();
+        let wins: Vec<bool> = handles.into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        // exactly one thread replaces the first value
+        assert_eq!(wins.iter().filter(|&&won| won).count(), 1);
+        let winner = wins.iter().position(|&won| won).unwrap() + 1;
+        assert_eq!(value(atomic.load(Ordering::Acquire)), winner as i32);
+        assert_eq!(Arc::strong_count(&first), 1);
+    });
+}
+
+#[test]
+fn store_races_load() {
+    model(|| {
+        let atomic = Arc::new(AtomicArc::new(0));
+
+        // neither value is referenced outside of the cell once it is stored
+        let writer = {
+            let atomic = atomic.clone();
+            thread::spawn(move || {
+                atomic.store(Arc::new(1), Ordering::Release);
+                drop(atomic.swap(Arc::new(2), Ordering::AcqRel));
+            })
+        };
+
+        let first = value(atomic.load(Ordering::Acquire));
+        let second = value(atomic.load(Ordering::Acquire));
+        assert!(first <= second);
+
+        writer.join().unwrap();
+        assert_eq!(value(atomic.load(Ordering::Acquire)), 2);
+    });
+}
+
+#[cfg(feature = "collections")]
+#[test]
+fn stack_producer_consumer() {
+    model(|| {
+        let stack = Arc::new(Stack::new());
+
+        let producer = {
+            let stack = stack.clone();
+            thread::spawn(move || {
+                stack.push(1);
+                stack.push(2);
+            })
+        };
+
+        // the pops race with the second push, which loads the node that they release
+        let consumer = {
+            let stack = stack.clone();
+            thread::spawn(move || {
+                (0..2).filter_map(|_| stack.pop()).collect::<Vec<_>>()
+            })
+        };
+
+        let mut popped = consumer.join().unwrap();
+        producer.join().unwrap();
+        while let Some(val) = stack.pop() {
+            popped.push(val);
+        }
+
+        popped.sort_unstable();
+        assert_eq!(popped, [1, 2]);
+    });
+}
+
+#[cfg(all(feature = "collections", feature = "std"))]
+#[test]
+fn concurrent_intern() {
+    model(|| {
+        // a single bucket, so that both threads exchange the same head
+        let set = Arc::new(Set::with_buckets(1));
+
+        let handles: Vec<_> = (0..2)
+            .map(|i| {
+                let set = set.clone();
+                thread::spawn(move || (set.intern(1), set.intern(i + 2)))
+            })
+            .collect();
+        let interned: Vec<_> = handles.into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        // both threads receive the value inserted first
+        assert!(Arc::ptr_eq(&interned[0].0, &interned[1].0));
+        assert!(Arc::ptr_eq(&set.get(&1).unwrap(), &interned[0].0));
+        assert_eq!(*set.get(&3).unwrap(), 3);
+    });
+}
@@ -0,0 +1,13 @@
+//! Checks that discarding the values returned by the pointer operations is
+//! reported by the `unused_must_use` lint.
+//!
+//! The expected diagnostics in `tests/ui` are regenerated with
+//! `TRYBUILD=overwrite cargo test --test must_use`.
+#![cfg(not(feature = "loom"))]
+
+#[test]
+#[cfg_attr(miri, ignore = "Miri cannot invoke the compiler")]
+fn must_use() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/must_use.rs");
+}
@@ -0,0 +1,49 @@
+//! Exercises the pointer types from a `#![no_std]` crate, so that
+//! `cargo test --no-default-features --features tag,collections` proves the library only
+//! depends on `core` and `alloc`.
+#![no_std]
+#![cfg(not(feature = "loom"))]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
+
+#[cfg(feature = "collections")]
+use nolock::stack::Stack;
+use nolock::sync::{Atomic, AtomicArc};
+
+/// Reads the value behind a loaded pointer, with or without `feature = "tag"`
+fn value(ptr: impl Into<Arc<i32>>) -> i32 {
+    *ptr.into()
+}
+
+#[test]
+fn atomic_arc_without_std() {
+    let atomic = AtomicArc::new(1);
+    let old = atomic.swap(Arc::new(2), Ordering::AcqRel);
+    assert_eq!(value(old), 1);
+    assert_eq!(value(atomic.load(Ordering::Acquire)), 2);
+}
+
+#[cfg(feature = "tag")]
+#[test]
+fn tagged_arc_without_std() {
+    use nolock::sync::TaggedArc;
+
+    let tagged = TaggedArc::from_arc_with_tag(Arc::new(3u64), 0b1);
+    assert_eq!(tagged.tag(), 0b1);
+    let atomic: AtomicArc<u64> = AtomicArc::from(tagged);
+    assert_eq!(atomic.load(Ordering::Acquire).tag(), 0b1);
+}
+
+#[cfg(feature = "collections")]
+#[test]
+fn stack_without_std() {
+    let stack = Stack::new();
+    stack.push(1);
+    stack.push(2);
+    assert_eq!(stack.pop(), Some(2));
+    assert_eq!(stack.pop(), Some(1));
+    assert_eq!(stack.pop(), None);
+}
@@ -0,0 +1,70 @@
+//! Checks at compile time that the pointer types are only `Send` and `Sync`
+//! when sharing the pointee between threads is sound.
+#![cfg(not(feature = "loom"))]
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use static_assertions::{assert_impl_all, assert_not_impl_any};
+
+use nolock::sync::{AtomicArc, AtomicOptionArc, AtomicPinArc};
+
+assert_impl_all!(AtomicArc<i32>: Send, Sync);
+assert_impl_all!(AtomicOptionArc<i32>: Send, Sync);
+assert_impl_all!(AtomicPinArc<i32>: Send, Sync);
+
+// not `Sync`
+assert_not_impl_any!(AtomicArc<Cell<i32>>: Send, Sync);
+assert_not_impl_any!(AtomicOptionArc<Cell<i32>>: Send, Sync);
+assert_not_impl_any!(AtomicPinArc<Cell<i32>>: Send, Sync);
+// neither `Send` nor `Sync`
+assert_not_impl_any!(AtomicArc<Rc<i32>>: Send, Sync);
+
+#[cfg(feature = "tag")]
+mod tag {
+    use super::*;
+    use nolock::sync::{AtomicWeak, Marked, TaggedArc, TaggedNonNull, TaggedWeak};
+
+    assert_impl_all!(TaggedArc<i32>: Send, Sync);
+    assert_impl_all!(TaggedWeak<i32>: Send, Sync);
+    assert_impl_all!(TaggedNonNull<i32>: Send, Sync);
+    assert_impl_all!(AtomicWeak<i32>: Send, Sync);
+    assert_impl_all!(Marked<i32>: Send, Sync);
+
+    assert_not_impl_any!(TaggedArc<Cell<i32>>: Send, Sync);
+    assert_not_impl_any!(TaggedWeak<Cell<i32>>: Send, Sync);
+    assert_not_impl_any!(TaggedNonNull<Cell<i32>>: Send, Sync);
+    assert_not_impl_any!(AtomicWeak<Cell<i32>>: Send, Sync);
+    assert_not_impl_any!(Marked<Cell<i32>>: Send, Sync);
+}
+
+#[cfg(feature = "collections")]
+mod collections {
+    use super::*;
+    use nolock::stack::Stack;
+
+    assert_impl_all!(Stack<i32>: Send, Sync);
+    assert_not_impl_any!(Stack<Rc<i32>>: Send, Sync);
+}
+
+#[cfg(feature = "dwcas")]
+mod dwcas {
+    use super::*;
+    use nolock::sync::{AtomicArcSlice, AtomicArcStamped};
+
+    assert_impl_all!(AtomicArcStamped<i32>: Send, Sync);
+    assert_impl_all!(AtomicArcSlice<i32>: Send, Sync);
+    assert_not_impl_any!(AtomicArcStamped<Cell<i32>>: Send, Sync);
+    assert_not_impl_any!(AtomicArcSlice<Cell<i32>>: Send, Sync);
+}
+
+#[cfg(feature = "crossbeam-epoch")]
+mod epoch {
+    use super::*;
+    use nolock::sync::{EpochArc, Snapshot};
+
+    assert_impl_all!(EpochArc<i32>: Send, Sync);
+    assert_not_impl_any!(EpochArc<Cell<i32>>: Send, Sync);
+    // keeps the current thread pinned
+    assert_not_impl_any!(Snapshot<'static, i32>: Send);
+}
@@ -0,0 +1,13 @@
+//! Checks that `TaggedArc::TAG_BITS` can reject types without tag bits at
+//! compile time.
+//!
+//! The expected diagnostics in `tests/ui` are regenerated with
+//! `TRYBUILD=overwrite cargo test --test tag_bits`.
+#![cfg(all(feature = "tag", not(feature = "loom")))]
+
+#[test]
+#[cfg_attr(miri, ignore = "Miri cannot invoke the compiler")]
+fn tag_bits() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/tag_bits.rs");
+}
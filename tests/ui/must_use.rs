@@ -0,0 +1,14 @@
+#![deny(unused_must_use)]
+
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+use nolock::sync::{Atomic, AtomicArc};
+
+fn main() {
+    let cell = AtomicArc::new(1);
+    cell.load(Ordering::Acquire);
+    cell.swap(Arc::new(2), Ordering::AcqRel);
+    cell.compare_exchange(Arc::new(2), Arc::new(3), Ordering::AcqRel, Ordering::Acquire);
+    cell.into_raw();
+}
@@ -0,0 +1,11 @@
+use nolock::sync::TaggedArc;
+
+/// Rejects types whose pointers have no room for a tag
+const fn assert_taggable<T>() {
+    assert!(TaggedArc::<T>::TAG_BITS > 0, "`T` cannot carry a tag");
+}
+
+const _: () = assert_taggable::<u32>();
+const _: () = assert_taggable::<u8>();
+
+fn main() {}